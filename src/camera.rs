@@ -1,40 +1,716 @@
+//! Cameras look from an eye point towards a target in a right-handed world, where x points
+//! right, y points up and z points back towards the viewer, so an unrotated camera looks
+//! down -z. Image rows go down the screen and columns go across it.
+
 use crate::{
-    image::{Image, Resolution},
+    image::{filter::Filter, Accumulation, Colour, Image, Resolution},
+    random::Random,
     ray::Ray,
     scene::Scene,
-    vector::HVector,
+    vector::{Direction, Point},
+};
+use std::{
+    f64::consts::PI,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
 };
-use ndarray::array;
+
+/// Width and height, in pixels, of the square tiles the image is rendered in
+const TILE_SIZE: usize = 16;
+
+/// How a fisheye lens spreads angles from the view direction across the image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FisheyeMapping {
+    /// Distance from the centre is proportional to the angle
+    Equidistant,
+    /// Distance from the centre is proportional to the sine of half the angle, preserving the
+    /// solid angle covered by each pixel
+    Equisolid,
+}
+
+/// How points on the image map onto rays leaving the camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// A pinhole camera, whose vertical field of view is in radians, below pi
+    Perspective { vertical_field_of_view: f64 },
+    /// Parallel rays from a view of the given height, in world units
+    Orthographic { height: f64 },
+    /// A circular image, fitted to the shorter side, whose edge is at half the field of view
+    /// from the view direction. The field of view is in radians, up to two pi.
+    Fisheye {
+        field_of_view: f64,
+        mapping: FisheyeMapping,
+    },
+    /// A full panorama, with longitude across the width and latitude up the height
+    Equirectangular,
+}
+
+impl Projection {
+    /// Panics if the projection cannot cover the image, before any rays are traced
+    fn validate(&self) {
+        if let Projection::Perspective {
+            vertical_field_of_view,
+        } = *self
+        {
+            assert!(
+                0.0 < vertical_field_of_view && vertical_field_of_view < PI,
+                "vertical field of view must be between 0 and pi radians"
+            );
+        }
+    }
+}
+
+/// A thin lens in front of the eye, which blurs everything away from the focal distance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lens {
+    pub aperture_radius: f64,
+    /// Distance along the view direction to the plane in perfect focus
+    pub focal_distance: f64,
+    /// Number of straight blades around the aperture, which give out of focus highlights
+    /// their polygonal shape, or None for a round aperture
+    pub blades: Option<usize>,
+}
+
+impl Lens {
+    /// A point on the aperture, relative to its centre, from a uniform sample of the unit square
+    fn sample(&self, [s, t]: [f64; 2]) -> [f64; 2] {
+        match self.blades {
+            Some(blades) if blades >= 3 => {
+                // pick the triangle between the centre and one blade, then a point within it
+                let blade = ((s * blades as f64) as usize).min(blades - 1);
+                let s = s * blades as f64 - blade as f64;
+                let corner = |i: usize| {
+                    let angle = 2.0 * PI * i as f64 / blades as f64;
+                    [angle.cos(), angle.sin()]
+                };
+                let ([x1, y1], [x2, y2]) = (corner(blade), corner(blade + 1));
+                let distance = self.aperture_radius * s.sqrt();
+                [
+                    distance * (x1 + t * (x2 - x1)),
+                    distance * (y1 + t * (y2 - y1)),
+                ]
+            }
+            _ => {
+                let distance = self.aperture_radius * s.sqrt();
+                let angle = 2.0 * PI * t;
+                [distance * angle.cos(), distance * angle.sin()]
+            }
+        }
+    }
+}
+
+/// Where in each pixel its samples are taken
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SamplePattern {
+    /// The centres of the cells of a regular grid over the pixel, without randomness
+    Grid,
+    /// Uniformly random positions anywhere in the pixel
+    Jittered,
+    /// One random position in each cell of a regular grid, which spreads samples more evenly
+    Stratified,
+}
+
+impl SamplePattern {
+    /// Offset from the pixel's top left corner of sample `index` out of `count`
+    fn offset(&self, index: usize, count: usize, random: &mut Random) -> (f64, f64) {
+        // as square a grid as possible with exactly one cell for each sample, so that none
+        // are left empty; a prime count gives a single row
+        let count = count.max(1);
+        let rows = (1..=count)
+            .take_while(|rows| rows * rows <= count)
+            .filter(|rows| count.is_multiple_of(*rows))
+            .last()
+            .unwrap_or(1);
+        let columns = count / rows;
+        let cell = ((index / columns) as f64, (index % columns) as f64);
+        let (row, column) = match self {
+            SamplePattern::Grid => (cell.0 + 0.5, cell.1 + 0.5),
+            SamplePattern::Jittered => {
+                return (random.next_f64(), random.next_f64());
+            }
+            SamplePattern::Stratified => (cell.0 + random.next_f64(), cell.1 + random.next_f64()),
+        };
+        (row / rows as f64, column / columns as f64)
+    }
+
+    /// The pattern for round `round` of samples in a pixel. A grid would take the same
+    /// positions in every round, so rounds after the first are stratified instead.
+    fn for_round(&self, round: usize) -> SamplePattern {
+        match self {
+            SamplePattern::Grid if round > 0 => SamplePattern::Stratified,
+            pattern => *pattern,
+        }
+    }
+}
+
+/// Settings for spending more samples on the pixels which need them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Samples taken in every pixel, and in each further round of samples
+    pub min_samples_per_pixel: usize,
+    pub max_samples_per_pixel: usize,
+    /// Largest acceptable standard error of a pixel's mean luminance, where white is 1
+    pub threshold: f64,
+}
 
 pub struct Camera {
-    position: HVector,
+    eye: Point,
+    /// Unit vector pointing right across the image plane
+    right: Direction,
+    /// Unit vector pointing up the image plane, perpendicular to `right` and `forward`
+    up: Direction,
+    /// Unit vector from the eye towards the target
+    forward: Direction,
+    pub projection: Projection,
+    /// Lens for depth of field with the perspective and orthographic projections, or None for
+    /// a pinhole which keeps everything in focus
+    pub lens: Option<Lens>,
+    /// Number of rays traced for each pixel, each through a different point on the lens;
+    /// 0 is treated as 1
+    pub samples_per_pixel: usize,
+    /// Instead of a fixed number of samples, keep sampling each pixel until its estimated
+    /// error is below a threshold
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    pub sample_pattern: SamplePattern,
+    /// How samples are weighed into the pixels around them
+    pub filter: Filter,
     resolution: Resolution,
+    /// Number of threads rendering tiles in parallel; 0 is treated as 1
+    pub threads: usize,
+}
+
+/// A rectangular block of pixels, rendered as one unit of work
+struct Tile {
+    row: usize,
+    column: usize,
+    height: usize,
+    width: usize,
+}
+
+impl Tile {
+    fn coordinates(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.row..self.row + self.height).flat_map(move |row| {
+            (self.column..self.column + self.width).map(move |column| (row, column))
+        })
+    }
 }
+
 impl Camera {
+    /// A camera at `position` looking down -z, with a vertical field of view of 90 degrees
     pub fn new(position: [f64; 3], resolution: Resolution) -> Camera {
+        let [x, y, z] = position;
+        Camera::look_at(
+            position,
+            [x, y, z - 1.0],
+            [0.0, 1.0, 0.0],
+            PI / 2.0,
+            resolution,
+        )
+    }
+
+    /// A camera at `eye` looking towards `target`, turned so that `up` points up the image as
+    /// closely as possible. The vertical field of view is in radians, and the horizontal one
+    /// follows from the aspect ratio of `resolution`, so that pixels stay square.
+    pub fn look_at(
+        eye: [f64; 3],
+        target: [f64; 3],
+        up: [f64; 3],
+        vertical_field_of_view: f64,
+        resolution: Resolution,
+    ) -> Camera {
+        let eye = Point::new(eye);
+        let forward = Point::new(target) - eye.clone();
+        assert!(forward.magnitude() > 0.0, "eye and target must differ");
+        let forward = forward.normalized();
+        let right = forward.cross(&Direction::new(up));
+        assert!(
+            right.magnitude() > 0.0,
+            "up must not be parallel to the view direction"
+        );
+        let right = right.normalized();
+        let up = right.cross(&forward);
         Camera {
-            position: HVector::new(position),
+            eye,
+            right,
+            up,
+            forward,
+            projection: Projection::Perspective {
+                vertical_field_of_view,
+            },
+            lens: None,
+            samples_per_pixel: 1,
+            adaptive_sampling: None,
+            sample_pattern: SamplePattern::Grid,
+            filter: Filter::Box,
             resolution,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
         }
     }
 
     pub fn generate_image(&self, scene: &Scene, depth: u8) -> Image {
-        let mut image = Image::new(&self.resolution);
-        let [x, y, z] = self.position.to_array();
-        let origin = HVector::new([x, y, -z + 1.0]);
-        for (coordinates, pixel) in image.pixels.indexed_iter_mut() {
-            let pixel_position = self.get_pixel_position(coordinates);
-            let from = origin.clone();
-            let direction = (pixel_position - from.clone()).normalized();
-            let ray = Ray { from, direction };
-            *pixel = scene.trace(&ray, depth);
+        self.projection.validate();
+        let tiles = self.get_tiles();
+        let next_tile = AtomicUsize::new(0);
+        // each thread claims the next unrendered tile until none are left
+        let mut rendered_tiles: Vec<(usize, Accumulation)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut rendered = vec![];
+                        loop {
+                            let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                            let Some(tile) = tiles.get(index) else {
+                                break;
+                            };
+                            rendered.push((index, self.render_tile(scene, tile, depth)));
+                        }
+                        rendered
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap()) // PANIC if a render thread panicked
+                .collect()
+        });
+
+        // in a fixed order, so the sums where tiles overlap do not depend on the threads
+        rendered_tiles.sort_by_key(|&(index, _)| index);
+        let Resolution { height, width } = self.resolution;
+        let mut accumulation = Accumulation::new((0, 0), height, width);
+        for (_, tile_accumulation) in rendered_tiles.iter() {
+            accumulation.merge(tile_accumulation);
         }
-        image
+        accumulation.to_image()
     }
-    fn get_pixel_position(&self, (row, column): (usize, usize)) -> HVector {
-        let [x, y, z] = self.position.to_array();
-        let row_position = row as f64 / ((self.resolution.height as f64 - 1.0) / 2.0) - 1.0;
-        let column_position = column as f64 / ((self.resolution.width as f64 - 1.0) / 2.0) - 1.0;
-        HVector::new([x + column_position, y - row_position, -z])
+
+    fn get_tiles(&self) -> Vec<Tile> {
+        let Resolution { height, width } = self.resolution;
+        let mut tiles = vec![];
+        for row in (0..height).step_by(TILE_SIZE) {
+            for column in (0..width).step_by(TILE_SIZE) {
+                tiles.push(Tile {
+                    row,
+                    column,
+                    height: TILE_SIZE.min(height - row),
+                    width: TILE_SIZE.min(width - column),
+                });
+            }
+        }
+        tiles
     }
+
+    /// Trace the samples of every pixel in the tile, accumulated over the tile and the
+    /// margin around it which the filter spreads them into
+    fn render_tile(&self, scene: &Scene, tile: &Tile, depth: u8) -> Accumulation {
+        let Resolution { height, width } = self.resolution;
+        let margin = (self.filter.radius() - 0.5).ceil().max(0.0) as usize;
+        let (top, left) = (
+            tile.row.saturating_sub(margin),
+            tile.column.saturating_sub(margin),
+        );
+        let bottom = (tile.row + tile.height + margin).min(height);
+        let right = (tile.column + tile.width + margin).min(width);
+        let mut accumulation = Accumulation::new((top, left), bottom - top, right - left);
+
+        // a single round of samples, unless adaptive sampling calls for more
+        let (min_samples, max_samples, threshold) = match self.adaptive_sampling {
+            Some(adaptive) => {
+                let min_samples = adaptive.min_samples_per_pixel.max(1);
+                let max_samples = adaptive.max_samples_per_pixel.max(min_samples);
+                (min_samples, max_samples, adaptive.threshold)
+            }
+            None => {
+                let samples = self.samples_per_pixel.max(1);
+                (samples, samples, 0.0)
+            }
+        };
+        for (row, column) in tile.coordinates() {
+            let mut random = Random::from_coordinates(row, column);
+            let (mut taken, mut round) = (0, 0);
+            while taken < max_samples {
+                // each round follows the sample pattern on its own
+                let samples = min_samples.min(max_samples - taken);
+                let pattern = self.sample_pattern.for_round(round);
+                for index in 0..samples {
+                    let (row_offset, column_offset) = pattern.offset(index, samples, &mut random);
+                    let position = (row as f64 + row_offset, column as f64 + column_offset);
+                    let lens_sample = [random.next_f64(), random.next_f64()];
+                    let colour = match self.get_ray(position, lens_sample) {
+                        Some(ray) => scene.trace(&ray, depth),
+                        None => Colour::BLACK, // outside a fisheye circle
+                    };
+                    accumulation.add_sample(position, colour, &self.filter);
+                }
+                taken += samples;
+                round += 1;
+                if accumulation.standard_error((row, column)) <= threshold {
+                    break;
+                }
+            }
+        }
+        accumulation
+    }
+
+    /// The ray through a point on the image, given in pixels from its top left corner, or
+    /// None if the projection does not cover that point. The lens sample, in the unit square,
+    /// picks the point on the lens the ray passes through.
+    fn get_ray(&self, (row, column): (f64, f64), lens_sample: [f64; 2]) -> Option<Ray> {
+        let Resolution { height, width } = self.resolution;
+        let (height, width) = (height as f64, width as f64);
+        // in [-1, 1] from the left and bottom edges to the right and top edges
+        let x = 2.0 * column / width - 1.0;
+        let y = 1.0 - 2.0 * row / height;
+        let aspect_ratio = width / height;
+        let ray = match self.projection {
+            Projection::Perspective {
+                vertical_field_of_view,
+            } => {
+                let half_height = (vertical_field_of_view / 2.0).tan();
+                let half_width = half_height * aspect_ratio;
+                let ray = Ray {
+                    from: self.eye.clone(),
+                    direction: self.get_direction(x * half_width, y * half_height, 1.0),
+                };
+                self.focus(ray, lens_sample)
+            }
+            Projection::Orthographic { height } => {
+                let offset = self.right.scale(x * height / 2.0 * aspect_ratio)
+                    + self.up.scale(y * height / 2.0);
+                let ray = Ray {
+                    from: self.eye.clone() + offset,
+                    direction: self.forward.clone(),
+                };
+                self.focus(ray, lens_sample)
+            }
+            Projection::Fisheye {
+                field_of_view,
+                mapping,
+            } => {
+                // scaled so that the circle touches the shorter sides
+                let (x, y) = if aspect_ratio > 1.0 {
+                    (x * aspect_ratio, y)
+                } else {
+                    (x, y / aspect_ratio)
+                };
+                let radius = x.hypot(y);
+                if radius > 1.0 {
+                    return None;
+                }
+                let angle = match mapping {
+                    FisheyeMapping::Equidistant => radius * field_of_view / 2.0,
+                    FisheyeMapping::Equisolid => {
+                        2.0 * (radius * (field_of_view / 4.0).sin()).asin()
+                    }
+                };
+                let (x, y) = if radius > 0.0 {
+                    (x / radius, y / radius)
+                } else {
+                    (0.0, 0.0)
+                };
+                Ray {
+                    from: self.eye.clone(),
+                    direction: self.get_direction(x * angle.sin(), y * angle.sin(), angle.cos()),
+                }
+            }
+            Projection::Equirectangular => {
+                let longitude = x * PI;
+                let latitude = y * PI / 2.0;
+                Ray {
+                    from: self.eye.clone(),
+                    direction: self.get_direction(
+                        latitude.cos() * longitude.sin(),
+                        latitude.sin(),
+                        latitude.cos() * longitude.cos(),
+                    ),
+                }
+            }
+        };
+        Some(ray)
+    }
+
+    /// Move the origin of a pinhole ray across the lens, keeping the point it sees on the
+    /// plane of focus
+    fn focus(&self, ray: Ray, lens_sample: [f64; 2]) -> Ray {
+        let Some(lens) = self.lens else {
+            return ray;
+        };
+        let distance = lens.focal_distance / ray.direction.dot(&self.forward);
+        let focus = ray.from.clone() + ray.direction.scale(distance);
+        let [x, y] = lens.sample(lens_sample);
+        let from = ray.from + self.right.scale(x) + self.up.scale(y);
+        Ray {
+            direction: (focus - from.clone()).normalized(),
+            from,
+        }
+    }
+
+    /// Unit vector from components along the right, up and forward directions
+    fn get_direction(&self, right: f64, up: f64, forward: f64) -> Direction {
+        (self.right.scale(right) + self.up.scale(up) + self.forward.scale(forward)).normalized()
+    }
+}
+
+#[test]
+fn test_look_at() {
+    let camera = Camera::look_at(
+        [1.0, 2.0, 3.0],
+        [1.0, 2.0, -7.0],
+        [0.0, 2.0, 0.0],
+        PI / 2.0,
+        Resolution {
+            width: 200,
+            height: 100,
+        },
+    );
+    let direction = |coordinates| {
+        camera
+            .get_ray(coordinates, [0.0; 2])
+            .unwrap()
+            .direction
+            .to_array()
+    };
+    assert_eq!(
+        camera
+            .get_ray((50.0, 100.0), [0.0; 2])
+            .unwrap()
+            .from
+            .to_array(),
+        [1.0, 2.0, 3.0]
+    );
+    assert_eq!(direction((50.0, 100.0)), [0.0, 0.0, -1.0]);
+
+    // 45 degrees up to the top edge, and twice as far across to the right edge
+    let [x, y, z] = direction((0.0, 100.0));
+    assert!(x.abs() < 1e-12 && (y + z).abs() < 1e-12 && y > 0.0);
+    let [x, y, z] = direction((50.0, 200.0));
+    assert!((x + 2.0 * z).abs() < 1e-12 && y.abs() < 1e-12 && x > 0.0);
+}
+
+#[test]
+fn test_projections() {
+    let mut camera = Camera::new(
+        [0.0, 0.0, 0.0],
+        Resolution {
+            width: 200,
+            height: 100,
+        },
+    );
+    let close = |a: [f64; 3], b: [f64; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-12);
+
+    camera.projection = Projection::Orthographic { height: 4.0 };
+    let ray = camera.get_ray((0.0, 200.0), [0.0; 2]).unwrap();
+    assert!(close(ray.from.to_array(), [4.0, 2.0, 0.0]));
+    assert!(close(ray.direction.to_array(), [0.0, 0.0, -1.0]));
+
+    for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+        camera.projection = Projection::Fisheye {
+            field_of_view: PI,
+            mapping,
+        };
+        // the edge of the circle is 90 degrees from the view direction
+        let ray = camera.get_ray((50.0, 150.0), [0.0; 2]).unwrap();
+        assert!(close(ray.direction.to_array(), [1.0, 0.0, 0.0]));
+        let ray = camera.get_ray((50.0, 100.0), [0.0; 2]).unwrap();
+        assert!(close(ray.direction.to_array(), [0.0, 0.0, -1.0]));
+        assert!(camera.get_ray((0.0, 0.0), [0.0; 2]).is_none());
+    }
+
+    camera.projection = Projection::Equirectangular;
+    let direction = |coordinates| {
+        camera
+            .get_ray(coordinates, [0.0; 2])
+            .unwrap()
+            .direction
+            .to_array()
+    };
+    assert!(close(direction((50.0, 100.0)), [0.0, 0.0, -1.0]));
+    assert!(close(direction((50.0, 150.0)), [1.0, 0.0, 0.0]));
+    assert!(close(direction((50.0, 0.0)), [0.0, 0.0, 1.0]));
+    assert!(close(direction((0.0, 30.0)), [0.0, 1.0, 0.0]));
+
+    // rejected once, before rendering, rather than by every ray
+    let wide = Projection::Perspective {
+        vertical_field_of_view: PI,
+    };
+    assert!(std::panic::catch_unwind(|| wide.validate()).is_err());
+    Projection::Equirectangular.validate();
+}
+
+#[test]
+fn test_lens() {
+    let mut camera = Camera::new(
+        [0.0, 0.0, 0.0],
+        Resolution {
+            width: 100,
+            height: 100,
+        },
+    );
+    for blades in [None, Some(6)] {
+        let lens = Lens {
+            aperture_radius: 0.5,
+            focal_distance: 4.0,
+            blades,
+        };
+        camera.lens = Some(lens);
+        for sample in [[0.0, 0.0], [0.3, 0.9], [0.99, 0.5]] {
+            let [x, y] = lens.sample(sample);
+            assert!(x.hypot(y) <= 0.5 + 1e-12);
+            // rays through the same pixel meet on the plane of focus
+            let ray = camera.get_ray((20.0, 70.0), sample).unwrap();
+            let distance = 4.0 / -ray.direction.to_array()[2];
+            let focus = (ray.from.clone() + ray.direction.scale(distance)).to_array();
+            let pinhole = camera.get_ray((20.0, 70.0), [0.0, 0.0]).unwrap();
+            let expected = pinhole
+                .direction
+                .scale(4.0 / -pinhole.direction.to_array()[2]);
+            for (a, b) in focus.iter().zip(expected.to_array()) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sample_pattern() {
+    let mut random = Random::new(1);
+    let offsets: Vec<(f64, f64)> = (0..6)
+        .map(|index| SamplePattern::Grid.offset(index, 6, &mut random))
+        .collect();
+    assert_eq!(offsets[0], (0.25, 1.0 / 6.0));
+    assert_eq!(offsets[5], (0.75, 5.0 / 6.0));
+    assert_eq!(SamplePattern::Grid.offset(0, 1, &mut random), (0.5, 0.5));
+    // counts which are not square still fill every cell of the grid, once
+    for count in [2, 5, 8, 12] {
+        let offsets: Vec<(f64, f64)> = (0..count)
+            .map(|index| SamplePattern::Grid.offset(index, count, &mut random))
+            .collect();
+        let distinct = |values: Vec<f64>| {
+            let mut values = values;
+            values.sort_by(f64::total_cmp);
+            values.dedup();
+            values.len()
+        };
+        let rows = distinct(offsets.iter().map(|offset| offset.0).collect());
+        let columns = distinct(offsets.iter().map(|offset| offset.1).collect());
+        assert_eq!(rows * columns, count);
+    }
+
+    for pattern in [SamplePattern::Jittered, SamplePattern::Stratified] {
+        for index in 0..9 {
+            let (row, column) = pattern.offset(index, 9, &mut random);
+            assert!((0.0..1.0).contains(&row) && (0.0..1.0).contains(&column));
+            if pattern == SamplePattern::Stratified {
+                assert_eq!((row * 3.0) as usize, index / 3);
+                assert_eq!((column * 3.0) as usize, index % 3);
+            }
+        }
+    }
+
+    // later adaptive rounds over a grid never take a position again
+    let mut positions: Vec<(f64, f64)> = vec![];
+    for round in 0..5 {
+        let pattern = SamplePattern::Grid.for_round(round);
+        for index in 0..4 {
+            let offset = pattern.offset(index, 4, &mut random);
+            assert!(!positions.contains(&offset));
+            positions.push(offset);
+        }
+    }
+}
+
+#[test]
+fn test_adaptive_sampling() {
+    use crate::scene::{
+        light::Light,
+        object::{
+            matrix::{rotation::Orientation, AffineTransformation},
+            Object, ObjectShape,
+        },
+    };
+    let sphere = Object::new(
+        ObjectShape::Sphere,
+        Some(AffineTransformation {
+            scale: [1.0; 3],
+            position: [0.0, 0.0, -3.0],
+            orientation: Orientation::IDENTITY,
+        }),
+        None,
+    );
+    let scene = Scene::new(vec![sphere], vec![Light::new([0.0, 5.0, 0.0])]);
+    let mut camera = Camera::new(
+        [0.0, 0.0, 0.0],
+        Resolution {
+            width: 32,
+            height: 32,
+        },
+    );
+    camera.sample_pattern = SamplePattern::Jittered;
+    camera.adaptive_sampling = Some(AdaptiveSampling {
+        min_samples_per_pixel: 4,
+        max_samples_per_pixel: 20,
+        threshold: 0.01,
+    });
+    let tile = Tile {
+        row: 0,
+        column: 0,
+        height: 32,
+        width: 32,
+    };
+    let accumulation = camera.render_tile(&scene, &tile, 1);
+    let counts: Vec<usize> = tile
+        .coordinates()
+        .map(|pixel| accumulation.sample_count(pixel))
+        .collect();
+    // the empty background converges at once, while the sphere's edge does not
+    assert_eq!(counts[0], 4);
+    assert!(counts.contains(&20));
+    assert!(counts.iter().all(|&count| (4..=20).contains(&count)));
+}
+
+#[test]
+fn test_threads_do_not_change_the_image() {
+    use crate::scene::{
+        light::Light,
+        object::{
+            matrix::{rotation::Orientation, AffineTransformation},
+            Object, ObjectShape,
+        },
+    };
+    let sphere = Object::new(
+        ObjectShape::Sphere,
+        Some(AffineTransformation {
+            scale: [1.0; 3],
+            position: [0.0, 0.0, -3.0],
+            orientation: Orientation::IDENTITY,
+        }),
+        None,
+    );
+    let scene = Scene::new(vec![sphere], vec![Light::new([0.0, 5.0, 0.0])]);
+    // several tiles, with jittered samples spread across their edges
+    let mut camera = Camera::new(
+        [0.0, 0.0, 0.0],
+        Resolution {
+            width: 40,
+            height: 24,
+        },
+    );
+    camera.samples_per_pixel = 4;
+    camera.sample_pattern = SamplePattern::Jittered;
+    camera.filter = Filter::MitchellNetravali {
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    };
+    let render = |camera: &Camera| -> Vec<[f64; 3]> {
+        let image = camera.generate_image(&scene, 1);
+        image
+            .pixels
+            .iter()
+            .map(|colour| [colour.red, colour.green, colour.blue])
+            .collect()
+    };
+    camera.threads = 1;
+    let single = render(&camera);
+    camera.threads = 4;
+    assert_eq!(render(&camera), single);
 }
@@ -1,5 +1,11 @@
 use ndarray::Array2;
-use std::ops::{Add, AddAssign};
+use std::ops::{Add, AddAssign, Mul};
+
+pub mod filter;
+use filter::Filter;
+
+pub mod output;
+use output::OutputTransform;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Colour {
@@ -21,16 +27,21 @@ impl Colour {
         blue: 1.0,
     };
 
-    pub fn to_pixel(&self) -> Pixel {
-        fn pixel_from_colour(colour: f64) -> u8 {
-            // TODO: assert!(0.0 >= pixel && pixel <= 1.0);
-            (255.0 * colour) as u8
-        }
-        Pixel {
-            red: pixel_from_colour(self.red),
-            green: pixel_from_colour(self.green),
-            blue: pixel_from_colour(self.blue),
-        }
+    /// Display values scaled onto [0, maxval], after the output transform
+    pub fn to_channels(&self, transform: &OutputTransform, maxval: u16) -> [u16; 3] {
+        let display = transform.apply(self);
+        [display.red, display.green, display.blue]
+            .map(|channel| (maxval as f64 * channel.clamp(0.0, 1.0)).round() as u16)
+    }
+
+    pub fn to_pixel(&self, transform: &OutputTransform) -> Pixel {
+        let [red, green, blue] = self.to_channels(transform, u8::MAX as u16).map(|c| c as u8);
+        Pixel { red, green, blue }
+    }
+
+    /// Perceived brightness, using the Rec. 709 weights
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
     }
 
     pub fn scale(&self, factor: f64) -> Colour {
@@ -54,6 +65,19 @@ impl Add for Colour {
     }
 }
 
+/// Component-wise, e.g. to filter light by a surface's colour
+impl Mul for Colour {
+    type Output = Colour;
+
+    fn mul(self, rhs: Colour) -> Self::Output {
+        Colour {
+            red: self.red * rhs.red,
+            green: self.green * rhs.green,
+            blue: self.blue * rhs.blue,
+        }
+    }
+}
+
 impl AddAssign for Colour {
     fn add_assign(&mut self, rhs: Colour) {
         *self = *self + rhs;
@@ -65,6 +89,7 @@ pub struct Resolution {
     pub width: usize,
 }
 
+#[derive(Clone, Debug)]
 pub struct Image {
     pub pixels: Array2<Colour>,
 }
@@ -74,8 +99,163 @@ impl Image {
             pixels: Array2::from_elem((resolution.height, resolution.width), Colour::BLACK),
         }
     }
+
+    pub fn to_pixels(&self, transform: &OutputTransform) -> Array2<Pixel> {
+        self.pixels.map(|colour| colour.to_pixel(transform))
+    }
+}
+
+/// Running mean and variance of the luminance of the samples taken within one pixel, using
+/// Welford's algorithm
+#[derive(Clone, Copy, Debug, Default)]
+struct SampleStatistics {
+    count: usize,
+    mean: f64,
+    /// Sum of squared differences from the mean
+    squared_deviations: f64,
+}
+
+impl SampleStatistics {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        let deviation = value - self.mean;
+        self.mean += deviation / self.count as f64;
+        self.squared_deviations += deviation * (value - self.mean);
+    }
+
+    /// Combine with the statistics of another set of samples
+    fn merge(&mut self, other: &SampleStatistics) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let deviation = other.mean - self.mean;
+        self.squared_deviations += other.squared_deviations
+            + deviation * deviation * (self.count * other.count) as f64 / count as f64;
+        self.mean += deviation * other.count as f64 / count as f64;
+        self.count = count;
+    }
+
+    /// Estimated standard deviation of the mean, which is infinite until there are two samples
+    fn standard_error(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        let variance = self.squared_deviations / (self.count - 1) as f64;
+        (variance / self.count as f64).sqrt()
+    }
+}
+
+/// Filter-weighted sums of samples over a block of pixels, resolved into an image once every
+/// sample has been added. The spread of the samples taken within each pixel is tracked too, to
+/// tell how far the pixel is from converging.
+pub struct Accumulation {
+    /// Row and column of the top left pixel of the block within the whole image
+    origin: (usize, usize),
+    sums: Array2<Colour>,
+    weights: Array2<f64>,
+    statistics: Array2<SampleStatistics>,
 }
 
+impl Accumulation {
+    pub fn new(origin: (usize, usize), height: usize, width: usize) -> Accumulation {
+        Accumulation {
+            origin,
+            sums: Array2::from_elem((height, width), Colour::BLACK),
+            weights: Array2::zeros((height, width)),
+            statistics: Array2::default((height, width)),
+        }
+    }
+
+    /// Index within the block of a pixel, given by its row and column in the whole image
+    fn get_index(&self, (row, column): (usize, usize)) -> Option<(usize, usize)> {
+        let (height, width) = self.sums.dim();
+        let index = (
+            row.checked_sub(self.origin.0)?,
+            column.checked_sub(self.origin.1)?,
+        );
+        if index.0 < height && index.1 < width {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Number of samples taken within a pixel of the whole image, or 0 outside the block
+    pub fn sample_count(&self, pixel: (usize, usize)) -> usize {
+        self.get_index(pixel)
+            .map_or(0, |index| self.statistics[index].count)
+    }
+
+    /// Estimated standard deviation of the mean luminance of the samples taken within a pixel
+    /// of the whole image, which is infinite until it has two samples
+    pub fn standard_error(&self, pixel: (usize, usize)) -> f64 {
+        self.get_index(pixel).map_or(f64::INFINITY, |index| {
+            self.statistics[index].standard_error()
+        })
+    }
+
+    /// Spread a sample, at a position in pixels from the top left corner of the whole image,
+    /// over the pixels of the block within the filter's radius of it
+    pub fn add_sample(&mut self, (row, column): (f64, f64), colour: Colour, filter: &Filter) {
+        let radius = filter.radius();
+        // pixel i has its centre at i + 0.5
+        let range = |position: f64, start: usize, length: usize| {
+            let first = (position - 0.5 - radius).ceil().max(start as f64) as usize;
+            let last = (position - 0.5 + radius).floor() + 1.0;
+            let last = last.clamp(start as f64, (start + length) as f64) as usize;
+            first..last.max(first)
+        };
+        if row >= 0.0 && column >= 0.0 {
+            if let Some(index) = self.get_index((row as usize, column as usize)) {
+                self.statistics[index].add(colour.luminance());
+            }
+        }
+        let (height, width) = self.sums.dim();
+        for pixel_row in range(row, self.origin.0, height) {
+            for pixel_column in range(column, self.origin.1, width) {
+                let weight = filter.weight(
+                    column - (pixel_column as f64 + 0.5),
+                    row - (pixel_row as f64 + 0.5),
+                );
+                if weight == 0.0 {
+                    continue;
+                }
+                let index = (pixel_row - self.origin.0, pixel_column - self.origin.1);
+                self.sums[index] += colour.scale(weight);
+                self.weights[index] += weight;
+            }
+        }
+    }
+
+    /// Add the sums and statistics of another block into the part of this one which it
+    /// overlaps
+    pub fn merge(&mut self, other: &Accumulation) {
+        for ((row, column), &sum) in other.sums.indexed_iter() {
+            let pixel = (row + other.origin.0, column + other.origin.1);
+            if let Some(index) = self.get_index(pixel) {
+                self.sums[index] += sum;
+                self.weights[index] += other.weights[(row, column)];
+                self.statistics[index].merge(&other.statistics[(row, column)]);
+            }
+        }
+    }
+
+    /// Divide each pixel's sum by its total weight, leaving pixels without any weight black
+    pub fn to_image(&self) -> Image {
+        let mut pixels = self.sums.clone();
+        for (pixel, &weight) in pixels.iter_mut().zip(self.weights.iter()) {
+            *pixel = if weight > 0.0 {
+                pixel.scale(1.0 / weight)
+            } else {
+                Colour::BLACK
+            };
+        }
+        Image { pixels }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Pixel {
     red: u8,
     green: u8,
@@ -86,3 +266,53 @@ impl Pixel {
         format!("{} {} {}", self.red, self.green, self.blue)
     }
 }
+
+#[test]
+fn test_accumulation() {
+    let tent = Filter::Tent;
+    let mut whole = Accumulation::new((0, 0), 2, 3);
+    let mut block = Accumulation::new((1, 1), 1, 2);
+    let grey = Colour::WHITE.scale(0.5);
+    // halfway between the centres of pixels (1, 1) and (1, 2)
+    block.add_sample((1.5, 2.0), grey, &tent);
+    whole.add_sample((0.5, 0.5), Colour::WHITE, &tent);
+    whole.merge(&block);
+    assert_eq!(whole.weights[(1, 1)], 0.5);
+    assert_eq!(whole.weights[(1, 2)], 0.5);
+    assert_eq!(whole.weights[(0, 0)], 1.0);
+    assert_eq!(whole.weights[(0, 1)], 0.0);
+
+    let image = whole.to_image();
+    assert_eq!(image.pixels[(1, 2)].green, 0.5);
+    assert_eq!(image.pixels[(0, 0)].red, 1.0);
+    assert_eq!(image.pixels[(0, 2)].blue, 0.0);
+}
+
+#[test]
+fn test_sample_statistics() {
+    let values = [0.2, 0.4, 0.9, 0.1, 0.5];
+    let mut all = SampleStatistics::default();
+    let (mut first, mut second) = (SampleStatistics::default(), SampleStatistics::default());
+    for (i, &value) in values.iter().enumerate() {
+        all.add(value);
+        if i < 2 {
+            first.add(value);
+        } else {
+            second.add(value);
+        }
+    }
+    first.merge(&second);
+    // sample variance 0.097, over 5 samples
+    let expected = (0.097f64 / 5.0).sqrt();
+    assert!((all.standard_error() - expected).abs() < 1e-12);
+    assert!((first.standard_error() - expected).abs() < 1e-12);
+    assert_eq!(first.count, 5);
+
+    let mut accumulation = Accumulation::new((4, 4), 2, 2);
+    accumulation.add_sample((4.2, 5.9), Colour::WHITE, &Filter::Box);
+    assert_eq!(accumulation.sample_count((4, 5)), 1);
+    assert_eq!(accumulation.standard_error((4, 5)), f64::INFINITY);
+    accumulation.add_sample((4.7, 5.1), Colour::WHITE, &Filter::Box);
+    assert_eq!(accumulation.standard_error((4, 5)), 0.0);
+    assert_eq!(accumulation.sample_count((0, 0)), 0);
+}
@@ -0,0 +1,96 @@
+/// A reconstruction filter, weighing how much a sample contributes to each pixel around it by
+/// its offset from the pixel's centre, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// Equal weight within the pixel the sample falls in
+    Box,
+    /// Weight falling linearly to zero one pixel away
+    Tent,
+    /// Bell curve, truncated at three standard deviations
+    Gaussian { standard_deviation: f64 },
+    /// Piecewise cubic reaching two pixels away, which sharpens with small negative lobes.
+    /// B = C = 1/3 is the usual compromise between blurring and ringing.
+    MitchellNetravali { b: f64, c: f64 },
+}
+
+impl Filter {
+    /// Distance, in pixels, beyond which samples have no weight
+    pub fn radius(&self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian { standard_deviation } => 3.0 * standard_deviation,
+            Filter::MitchellNetravali { .. } => 2.0,
+        }
+    }
+
+    /// Weight of a sample at an offset of (x, y) from a pixel centre
+    pub fn weight(&self, x: f64, y: f64) -> f64 {
+        self.weight_1d(x) * self.weight_1d(y)
+    }
+
+    fn weight_1d(&self, x: f64) -> f64 {
+        match *self {
+            // half-open, so a sample on an edge only counts towards one pixel
+            Filter::Box => {
+                if (-0.5..0.5).contains(&x) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Tent => (1.0 - x.abs()).max(0.0),
+            Filter::Gaussian { standard_deviation } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * standard_deviation.powi(2))).exp();
+                // shifted down to reach zero at the radius
+                (gaussian(x) - gaussian(self.radius())).max(0.0)
+            }
+            Filter::MitchellNetravali { b, c } => {
+                let x = x.abs();
+                let value = if x < 1.0 {
+                    (12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                        + (6.0 - 2.0 * b)
+                } else if x < 2.0 {
+                    (-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x.powi(2)
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c)
+                } else {
+                    0.0
+                };
+                value / 6.0
+            }
+        }
+    }
+}
+
+#[test]
+fn test_weight() {
+    let filters = [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian {
+            standard_deviation: 0.5,
+        },
+        Filter::MitchellNetravali {
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        },
+    ];
+    for filter in filters {
+        assert!(filter.weight(0.0, 0.0) > 0.0);
+        assert_eq!(filter.weight(filter.radius() + 0.01, 0.0), 0.0);
+        assert_eq!(filter.weight(0.2, -0.3), filter.weight(-0.3, 0.2));
+    }
+    assert_eq!(Filter::Box.weight(-0.5, 0.0), 1.0);
+    assert_eq!(Filter::Box.weight(0.5, 0.0), 0.0);
+    assert_eq!(Filter::Tent.weight(0.5, 0.5), 0.25);
+    // Mitchell-Netravali weights of samples spaced a pixel apart sum to 1
+    let mitchell = Filter::MitchellNetravali {
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    };
+    let sum: f64 = (-2..=2).map(|i| mitchell.weight_1d(i as f64 + 0.25)).sum();
+    assert!((sum - 1.0).abs() < 1e-12);
+}
@@ -0,0 +1,152 @@
+use crate::image::Colour;
+use std::default::Default;
+
+/// Curve compressing linear light, which may be brighter than white, into [0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapping {
+    /// Cut off anything brighter than white
+    Clamp,
+    /// x / (1 + x), which never quite reaches white
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve
+    AcesFilmic,
+    /// John Hable's filmic curve from Uncharted 2, with a white point of 11.2
+    Hable,
+}
+
+impl ToneMapping {
+    fn apply(&self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        match self {
+            ToneMapping::Clamp => x.min(1.0),
+            ToneMapping::Reinhard => x / (1.0 + x),
+            ToneMapping::AcesFilmic => {
+                let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+                mapped.clamp(0.0, 1.0)
+            }
+            ToneMapping::Hable => {
+                const WHITE_POINT: f64 = 11.2;
+                const EXPOSURE_BIAS: f64 = 2.0;
+                let curve = |x: f64| {
+                    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+                    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+                };
+                (curve(EXPOSURE_BIAS * x) / curve(WHITE_POINT)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// Encoding of tone mapped values for display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferFunction {
+    /// Values are written as they are, for viewers which expect linear light
+    Linear,
+    /// The sRGB curve, which most displays and image viewers expect
+    Srgb,
+}
+
+impl TransferFunction {
+    /// From linear light to display values
+    pub fn encode(&self, x: f64) -> f64 {
+        match self {
+            TransferFunction::Linear => x,
+            TransferFunction::Srgb => {
+                if x <= 0.0031308 {
+                    12.92 * x
+                } else {
+                    1.055 * x.powf(1.0 / 2.4) - 0.055
+                }
+            }
+        }
+    }
+
+    /// From display values, such as those read from an image file, back to linear light
+    pub fn decode(&self, x: f64) -> f64 {
+        match self {
+            TransferFunction::Linear => x,
+            TransferFunction::Srgb => {
+                if x <= 0.04045 {
+                    x / 12.92
+                } else {
+                    ((x + 0.055) / 1.055).powf(2.4)
+                }
+            }
+        }
+    }
+}
+
+/// Conversion of the linear light a scene renders to into display values in [0, 1]: scale by
+/// the exposure, compress by the tone mapping, then encode by the transfer function
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputTransform {
+    /// Brightening, in stops, so that each +1 doubles the light
+    pub exposure: f64,
+    pub tone_mapping: ToneMapping,
+    pub transfer_function: TransferFunction,
+}
+
+impl OutputTransform {
+    pub const DEFAULT: OutputTransform = OutputTransform {
+        exposure: 0.0,
+        tone_mapping: ToneMapping::Clamp,
+        transfer_function: TransferFunction::Srgb,
+    };
+
+    pub fn apply(&self, colour: &Colour) -> Colour {
+        let scale = self.exposure.exp2();
+        let channel = |x: f64| {
+            self.transfer_function
+                .encode(self.tone_mapping.apply(x * scale))
+        };
+        Colour {
+            red: channel(colour.red),
+            green: channel(colour.green),
+            blue: channel(colour.blue),
+        }
+    }
+}
+
+impl Default for OutputTransform {
+    fn default() -> OutputTransform {
+        OutputTransform::DEFAULT
+    }
+}
+
+#[test]
+fn test_output_transform() {
+    let operators = [
+        ToneMapping::Clamp,
+        ToneMapping::Reinhard,
+        ToneMapping::AcesFilmic,
+        ToneMapping::Hable,
+    ];
+    for tone_mapping in operators {
+        assert_eq!(tone_mapping.apply(-1.0), tone_mapping.apply(0.0));
+        assert!(tone_mapping.apply(0.0).abs() < 1e-3);
+        // brighter stays brighter, and never beyond white
+        let values: Vec<f64> = (0..100)
+            .map(|i| tone_mapping.apply(i as f64 * 0.2))
+            .collect();
+        assert!(values.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(values.iter().all(|&value| value <= 1.0));
+    }
+    assert_eq!(ToneMapping::Reinhard.apply(1.0), 0.5);
+    assert_eq!(ToneMapping::Hable.apply(11.2 / 2.0), 1.0);
+
+    let srgb = TransferFunction::Srgb;
+    assert_eq!(srgb.encode(0.0), 0.0);
+    assert!((srgb.encode(1.0) - 1.0).abs() < 1e-12);
+    assert!((srgb.encode(0.214) - 0.5).abs() < 1e-3);
+    for x in [0.001, 0.3, 0.9] {
+        assert!((srgb.decode(srgb.encode(x)) - x).abs() < 1e-12);
+    }
+
+    let transform = OutputTransform {
+        exposure: 1.0,
+        tone_mapping: ToneMapping::Clamp,
+        transfer_function: TransferFunction::Linear,
+    };
+    let grey = transform.apply(&Colour::WHITE.scale(0.25));
+    assert_eq!([grey.red, grey.green, grey.blue], [0.5; 3]);
+}
@@ -1,6 +1,7 @@
 pub mod camera;
 pub mod image;
 pub mod ppm;
+pub mod random;
 pub mod ray;
 pub mod scene;
 pub mod vector;
@@ -1,16 +1,25 @@
 use ndarray::array;
 use ray_tracer::{
-    camera::Camera,
-    image::{Colour, Resolution},
+    camera::{AdaptiveSampling, Camera, SamplePattern},
+    image::{filter::Filter, Colour, Resolution},
     ppm::writer::write_to_ppm,
     scene::{
         light::Light,
         object::{
-            material::Material, matrix::AffineTransformation, Intersectable, Object, ObjectShape::*,
+            material::{
+                texture::{Texture, TextureSpace},
+                Material, SpecularModel,
+            },
+            matrix::{
+                rotation::{EulerOrder, Orientation},
+                AffineTransformation,
+            },
+            Intersectable, Object,
+            ObjectShape::*,
         },
         Scene,
     },
-    vector::HVector,
+    vector::Point,
 };
 use std::{f64::consts::PI, io};
 
@@ -20,38 +29,48 @@ fn main() -> io::Result<()> {
         Sphere,
         Some(AffineTransformation {
             scale: [1.0, 3.0, 1.0],
-            position: [1.0, 0.5, 3.0],
-            orientation: (0.0, 0.0),
+            position: [1.0, 0.5, -3.0],
+            orientation: Orientation::IDENTITY,
         }),
-        Some(Material::new(
-            0.5,
-            0.3,
-            0.2,
-            0.7,
-            Colour {
-                red: 1.0,
-                green: 0.5,
-                blue: 0.5,
+        Some(Material {
+            reflectivity: 0.3,
+            colour: Texture::Marble {
+                colours: [
+                    Colour {
+                        red: 1.0,
+                        green: 0.5,
+                        blue: 0.5,
+                    },
+                    Colour::WHITE,
+                ],
+                scale: 0.5,
+                octaves: 4,
+                turbulence: 1.5,
+                space: TextureSpace::Object,
             },
-        )),
+            ..Material::new(0.5, 0.3, 0.2, 16.0, Colour::WHITE)
+        }),
     );
     let triangle = Object::new(
         Triangle(
             // equilateral
-            HVector::new([-1.0, 0.0, 0.0]),
-            HVector::new([1.0, 0.0, 0.0]),
-            HVector::new([0.0, 3.4641016151377545870548926830117, 0.0]),
+            Point::new([-1.0, 0.0, 0.0]),
+            Point::new([1.0, 0.0, 0.0]),
+            Point::new([0.0, 3.4641016151377545870548926830117, 0.0]),
         ),
         Some(AffineTransformation {
             scale: [1.0, 1.0, 1.0],
-            position: [-1.0, 0.5, 3.0],
-            orientation: (PI / 6.0, PI / 4.0),
+            position: [-1.0, 0.5, -3.0],
+            orientation: Orientation::Euler {
+                angles: [0.0, PI / 6.0, PI / 4.0],
+                order: EulerOrder::Yzx,
+            },
         }),
         Some(Material::new(
             0.2,
             0.2,
             0.6,
-            0.85,
+            8.0,
             Colour {
                 red: 0.0,
                 green: 1.0,
@@ -59,16 +78,78 @@ fn main() -> io::Result<()> {
             },
         )),
     );
-    let light = Light::new([-3.0, 20.0, 1.0]);
-    let scene = Scene::new(vec![sphere, triangle], vec![light]);
-    let camera = Camera::new(
-        [0.0, 0.0, 0.0],
+    let glass = Object::new(
+        Sphere,
+        Some(AffineTransformation {
+            scale: [0.6, 0.6, 0.6],
+            position: [-0.3, -0.4, -2.0],
+            orientation: Orientation::IDENTITY,
+        }),
+        Some(Material {
+            transparency: 0.9,
+            refractive_index: 1.5,
+            specular_model: SpecularModel::BlinnPhong,
+            ..Material::new(0.1, 0.1, 0.8, 128.0, Colour::WHITE)
+        }),
+    );
+    let floor = Object::new(
+        Plane,
+        Some(AffineTransformation {
+            scale: [1.0, 1.0, 1.0],
+            position: [0.0, -1.5, 0.0],
+            orientation: Orientation::IDENTITY,
+        }),
+        Some(Material {
+            colour: Texture::Checkerboard {
+                colours: [Colour::WHITE.scale(0.2), Colour::WHITE.scale(0.8)],
+                scale: 1.0,
+                space: TextureSpace::TextureCoordinates,
+            },
+            ..Material::new(0.3, 0.7, 0.0, 1.0, Colour::WHITE)
+        }),
+    );
+    let cone = Object::new(
+        Cone { capped: true },
+        Some(AffineTransformation {
+            scale: [0.5, 0.75, 0.5],
+            position: [-2.0, -0.75, -4.0],
+            orientation: Orientation::IDENTITY,
+        }),
+        Some(Material::new(
+            0.2,
+            0.6,
+            0.2,
+            16.0,
+            Colour {
+                red: 0.3,
+                green: 0.4,
+                blue: 1.0,
+            },
+        )),
+    );
+    let light = Light::new([-3.0, 20.0, -1.0]);
+    let scene = Scene::new(vec![sphere, triangle, glass, floor, cone], vec![light]);
+    let mut camera = Camera::look_at(
+        [0.0, 0.0, 1.0],
+        [0.0, 0.5, -3.0],
+        [0.0, 1.0, 0.0],
+        PI / 3.0,
         Resolution {
             width: 256,
             height: 144,
         },
     );
-    let image = camera.generate_image(&scene, 0);
-    write_to_ppm(image, "test.ppm")?;
+    camera.adaptive_sampling = Some(AdaptiveSampling {
+        min_samples_per_pixel: 4,
+        max_samples_per_pixel: 64,
+        threshold: 0.005,
+    });
+    camera.sample_pattern = SamplePattern::Stratified;
+    camera.filter = Filter::MitchellNetravali {
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    };
+    let image = camera.generate_image(&scene, 4);
+    write_to_ppm(&image, "test.ppm")?;
     Ok(())
 }
@@ -0,0 +1,168 @@
+use crate::image::{Colour, Image, Resolution};
+use std::fs::File;
+use std::io::{self, Read};
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads the whitespace separated fields of a PPM file, skipping comments
+struct Fields<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn next_field(&mut self) -> io::Result<&'a [u8]> {
+        loop {
+            match self.data.get(self.position) {
+                Some(byte) if byte.is_ascii_whitespace() => self.position += 1,
+                // comments run to the end of the line
+                Some(b'#') => {
+                    while !matches!(self.data.get(self.position), Some(b'\n' | b'\r') | None) {
+                        self.position += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err(invalid_data("unexpected end of PPM data")),
+            }
+        }
+        let start = self.position;
+        while matches!(self.data.get(self.position), Some(byte) if !byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+        Ok(&self.data[start..self.position])
+    }
+
+    fn next_number(&mut self) -> io::Result<usize> {
+        let field = self.next_field()?;
+        std::str::from_utf8(field)
+            .ok()
+            .and_then(|field| field.parse().ok())
+            .ok_or_else(|| invalid_data("invalid number in PPM data"))
+    }
+}
+
+/// Read a plain "P3" or raw "P6" PPM image from any source, with 8 or 16 bits per channel.
+/// Values are scaled onto [0, 1] as they are stored, without decoding any transfer function.
+pub fn read_ppm<R: Read>(mut source: R) -> io::Result<Image> {
+    let mut data = vec![];
+    source.read_to_end(&mut data)?;
+    let mut fields = Fields {
+        data: &data,
+        position: 0,
+    };
+    let magic = fields.next_field()?;
+    if magic != b"P3" && magic != b"P6" {
+        return Err(invalid_data("not a P3 or P6 PPM image"));
+    }
+    let width = fields.next_number()?;
+    let height = fields.next_number()?;
+    let maxval = fields.next_number()?;
+    if maxval == 0 || maxval > 65535 {
+        return Err(invalid_data("PPM maxval must be between 1 and 65535"));
+    }
+
+    // the header may be corrupt or hostile, so sizes taken from it must not overflow
+    let too_large = || invalid_data("PPM image too large");
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(too_large)?;
+    let values: Vec<usize> = if magic == b"P3" {
+        (0..count)
+            .map(|_| fields.next_number())
+            .collect::<io::Result<_>>()?
+    } else {
+        // a single whitespace character separates the header from the raster
+        let start = fields.position + 1;
+        let bytes_per_value = if maxval < 256 { 1 } else { 2 };
+        let end = count
+            .checked_mul(bytes_per_value)
+            .and_then(|length| length.checked_add(start))
+            .ok_or_else(too_large)?;
+        let raster = data
+            .get(start..end)
+            .ok_or_else(|| invalid_data("unexpected end of PPM data"))?;
+        raster
+            .chunks(bytes_per_value)
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .fold(0, |value, &byte| value << 8 | byte as usize)
+            })
+            .collect()
+    };
+    if values.iter().any(|&value| value > maxval) {
+        return Err(invalid_data("PPM value exceeds maxval"));
+    }
+
+    let mut image = Image::new(&Resolution { height, width });
+    for (pixel, channels) in image.pixels.iter_mut().zip(values.chunks(3)) {
+        let [red, green, blue] = [0, 1, 2].map(|i| channels[i] as f64 / maxval as f64);
+        *pixel = Colour { red, green, blue };
+    }
+    Ok(image)
+}
+
+pub fn read_from_ppm(filename: &str) -> io::Result<Image> {
+    read_ppm(File::open(filename)?)
+}
+
+#[test]
+fn test_read_ppm() {
+    let image =
+        read_ppm(&b"P3\n# a comment\n2 1 # and another\n255\n255 0 51\n0 255 0\n"[..]).unwrap();
+    assert_eq!(image.pixels.dim(), (1, 2));
+    assert_eq!(image.pixels[(0, 0)].red, 1.0);
+    assert_eq!(image.pixels[(0, 0)].blue, 0.2);
+    assert_eq!(image.pixels[(0, 1)].green, 1.0);
+
+    let image = read_ppm(&b"P6 1 2 255\n\xff\x00\x00\x00\x33\xff"[..]).unwrap();
+    assert_eq!(image.pixels[(1, 0)].green, 0.2);
+    let image = read_ppm(&b"P6 1 1 65535\n\xff\xff\x00\x00\x33\x33"[..]).unwrap();
+    assert_eq!(image.pixels[(0, 0)].red, 1.0);
+    assert_eq!(image.pixels[(0, 0)].blue, 0.2);
+
+    assert!(read_ppm(&b"P5 1 1 255\n\x00"[..]).is_err());
+    assert!(read_ppm(&b"P3 1 1 255\n0 0"[..]).is_err());
+    assert!(read_ppm(&b"P3 1 1 15\n0 0 16"[..]).is_err());
+    assert!(read_ppm(&b"P6 2 1 255\n\x00\x00\x00"[..]).is_err());
+    // sizes which overflow, rather than only running past the end of the data
+    for header in [
+        &b"P6 4294967296 4294967296 255\n\x00"[..],
+        &b"P6 3074457345618258603 1 65535\n\x00"[..],
+        &b"P3 18446744073709551615 2 255\n0"[..],
+    ] {
+        let error = read_ppm(header).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "PPM image too large");
+    }
+}
+
+#[test]
+fn test_read_written_ppm() {
+    use crate::image::output::{OutputTransform, ToneMapping, TransferFunction};
+    use crate::ppm::writer::{write_ppm, BitDepth, Encoding};
+    let linear = OutputTransform {
+        exposure: 0.0,
+        tone_mapping: ToneMapping::Clamp,
+        transfer_function: TransferFunction::Linear,
+    };
+    let mut image = Image::new(&Resolution {
+        height: 3,
+        width: 2,
+    });
+    image.pixels[(2, 1)] = Colour {
+        red: 0.2,
+        green: 0.4,
+        blue: 1.0,
+    };
+    for encoding in [Encoding::Ascii, Encoding::Binary] {
+        let mut output = vec![];
+        write_ppm(&image, &mut output, encoding, BitDepth::Sixteen, &linear).unwrap();
+        let read = read_ppm(&output[..]).unwrap();
+        assert_eq!(read.pixels.dim(), (3, 2));
+        assert!((read.pixels[(2, 1)].green - 0.4).abs() < 1e-4);
+    }
+}
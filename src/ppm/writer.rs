@@ -1,18 +1,143 @@
-use crate::image::Image;
+use crate::image::{output::OutputTransform, Image};
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, BufWriter, Write};
 
-pub fn write_to_ppm(image: Image, filename: &str) -> io::Result<()> {
-    let mut image_file = File::create(filename)?;
+/// How the pixel values of a PPM file are stored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    /// Plain "P3" files, with values written out as decimal text
+    Ascii,
+    /// Raw "P6" files, with values stored as bytes
+    Binary,
+}
+
+/// Number of bits per colour channel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    /// Two bytes per value in binary files, most significant first
+    Sixteen,
+}
+
+impl BitDepth {
+    /// The largest value of a channel
+    pub fn maxval(&self) -> u16 {
+        match self {
+            BitDepth::Eight => 255,
+            BitDepth::Sixteen => 65535,
+        }
+    }
+}
+
+/// Write an image in PPM format to any sink, such as a file, stdout or a `Vec<u8>`, with its
+/// colours converted for display by `transform`
+pub fn write_ppm<W: Write>(
+    image: &Image,
+    sink: W,
+    encoding: Encoding,
+    bit_depth: BitDepth,
+    transform: &OutputTransform,
+) -> io::Result<()> {
+    let mut sink = BufWriter::new(sink);
+    let magic = match encoding {
+        Encoding::Ascii => "P3",
+        Encoding::Binary => "P6",
+    };
+    let maxval = bit_depth.maxval();
     let width = image.pixels.ncols();
     let height = image.pixels.nrows();
-    write!(image_file, "P3\n{} {}\n255\n", width, height)?;
+    write!(sink, "{}\n{} {}\n{}\n", magic, width, height, maxval)?;
     for row in image.pixels.rows() {
         for colour in row {
-            let pixel_string = colour.to_pixel().to_string();
-            write!(image_file, "{}\t", pixel_string)?;
+            let channels = colour.to_channels(transform, maxval);
+            match (encoding, bit_depth) {
+                (Encoding::Ascii, _) => {
+                    let [red, green, blue] = channels;
+                    write!(sink, "{} {} {}\t", red, green, blue)?
+                }
+                (Encoding::Binary, BitDepth::Eight) => {
+                    sink.write_all(&channels.map(|c| c as u8))?
+                }
+                (Encoding::Binary, BitDepth::Sixteen) => {
+                    for channel in channels {
+                        sink.write_all(&channel.to_be_bytes())?;
+                    }
+                }
+            }
+        }
+        if encoding == Encoding::Ascii {
+            writeln!(sink)?;
         }
-        write!(image_file, "\n")?;
     }
-    Ok(())
+    sink.flush()
+}
+
+/// Write an image to a plain 8-bit PPM file, sRGB encoded. Use `write_ppm` for smaller binary
+/// or 16-bit files.
+pub fn write_to_ppm(image: &Image, filename: &str) -> io::Result<()> {
+    write_ppm(
+        image,
+        File::create(filename)?,
+        Encoding::Ascii,
+        BitDepth::Eight,
+        &OutputTransform::DEFAULT,
+    )
+}
+
+#[test]
+fn test_write_ppm() {
+    use crate::image::{
+        output::{ToneMapping, TransferFunction},
+        Colour, Resolution,
+    };
+    // linear values, to check the encoding alone
+    let linear = OutputTransform {
+        exposure: 0.0,
+        tone_mapping: ToneMapping::Clamp,
+        transfer_function: TransferFunction::Linear,
+    };
+    let mut image = Image::new(&Resolution {
+        height: 1,
+        width: 2,
+    });
+    image.pixels[(0, 0)] = Colour {
+        red: 1.0,
+        green: 0.5,
+        blue: -1.0,
+    };
+
+    let mut output = vec![];
+    write_ppm(
+        &image,
+        &mut output,
+        Encoding::Ascii,
+        BitDepth::Eight,
+        &linear,
+    )
+    .unwrap();
+    assert_eq!(output, b"P3\n2 1\n255\n255 128 0\t0 0 0\t\n");
+
+    let mut output = vec![];
+    write_ppm(
+        &image,
+        &mut output,
+        Encoding::Binary,
+        BitDepth::Eight,
+        &linear,
+    )
+    .unwrap();
+    assert_eq!(output, b"P6\n2 1\n255\n\xff\x80\x00\x00\x00\x00");
+
+    let mut output = vec![];
+    write_ppm(
+        &image,
+        &mut output,
+        Encoding::Binary,
+        BitDepth::Sixteen,
+        &linear,
+    )
+    .unwrap();
+    assert_eq!(&output[..15], b"P6\n2 1\n65535\n\xff\xff");
+    assert_eq!(&output[15..17], b"\x80\x00");
+    assert_eq!(output.len(), 13 + 2 * 2 * 3);
 }
@@ -0,0 +1,43 @@
+/// A small, fast pseudo-random number generator (SplitMix64). Renders seed one per pixel from
+/// its coordinates, so that images do not depend on how work is split between threads.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    /// A generator seeded from a pair of coordinates, such as a pixel's row and column
+    pub fn from_coordinates(row: usize, column: usize) -> Random {
+        let mut random = Random::new(((row as u64) << 32) ^ column as u64);
+        random.next_u64(); // spread the seed out, so neighbouring pixels are not correlated
+        random
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[test]
+fn test_next_f64() {
+    let mut random = Random::from_coordinates(3, 4);
+    let samples: Vec<f64> = (0..10000).map(|_| random.next_f64()).collect();
+    assert!(samples.iter().all(|&sample| (0.0..1.0).contains(&sample)));
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    assert!((mean - 0.5).abs() < 0.02);
+
+    let mut again = Random::from_coordinates(3, 4);
+    assert_eq!(again.next_f64(), samples[0]);
+}
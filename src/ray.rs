@@ -1,24 +1,28 @@
 use crate::{
     scene::object::material::Material,
-    vector::{HVector, Vector3},
+    vector::{Direction, Normal, Point, Vector3},
 };
 
 pub struct Ray {
-    pub from: HVector,
-    pub direction: HVector,
+    pub from: Point,
+    pub direction: Direction,
 }
 
 impl Ray {
     pub fn new(from: Vector3, direction: Vector3) -> Ray {
         Ray {
-            from: from.to_homo_vector(),
-            direction: direction.to_homo_vector(),
+            from: Point::from_hvector(from.to_homo_vector()),
+            direction: Direction::from_hvector(direction.to_homo_vector()),
         }
     }
 }
 
 pub struct Hit<'a> {
-    pub normal: Ray,
+    pub point: Point,
+    /// Facing out of the surface, of unit length once the hit leaves the object
+    pub normal: Normal,
+    /// The hit point in the space of the shape that was hit, before any transformation
+    pub object_point: Point,
     pub texture_coordinates: [f64; 2],
     pub material: Option<&'a Material>,
 }
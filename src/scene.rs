@@ -2,59 +2,325 @@ pub mod light;
 pub mod object;
 use self::{
     light::Light,
-    object::{intersection::find_closest_intersection, Object},
+    object::{bvh::BoundingVolumeHierarchy, Object},
 };
 use crate::{
     image::Colour,
     ray::{Hit, Ray},
-    vector::HVector,
+    vector::{Direction, Normal},
 };
 
+/// Distance secondary rays are moved off the surface they leave, to avoid re-hitting it
+const SURFACE_OFFSET: f64 = 1e-7;
+
+/// Scenes are shared by reference between render threads, so everything they hold,
+/// including objects and their materials, must be `Sync`.
 pub struct Scene {
-    objects: Vec<Object>,
+    objects: BoundingVolumeHierarchy<Object>,
     lights: Vec<Light>,
 }
 
 impl Scene {
     pub fn new(objects: Vec<Object>, lights: Vec<Light>) -> Scene {
-        Scene { objects, lights }
+        Scene {
+            objects: BoundingVolumeHierarchy::new(objects),
+            lights,
+        }
     }
     pub fn trace(&self, ray: &Ray, depth: u8) -> Colour {
-        match find_closest_intersection(&self.objects, ray) {
+        match self.objects.closest_intersection(ray) {
             Some(hit) => self.get_colour(&ray.direction, &hit, depth),
             None => Colour::BLACK,
         }
     }
-    pub fn get_colour(&self, direction: &HVector, hit: &Hit, depth: u8) -> Colour {
-        //let reflection = if depth == 0 {
-        //    Colour::BLACK
-        //} else {
-        //    Colour::BLACK // TODO: recurse
-        //};
-        //let refraction = Colour::BLACK; // TODO
+    pub fn get_colour(&self, direction: &Direction, hit: &Hit, depth: u8) -> Colour {
         let material = hit.material.unwrap(); // PANIC if hit has no material
 
+        // flip the normal of surfaces hit from behind, e.g. exiting a sphere
+        let entering = hit.normal.dot(direction) < 0.0;
+        let normal = if entering {
+            hit.normal.clone()
+        } else {
+            hit.normal.reverse()
+        };
+
+        let colour = material.get_colour(hit);
+
         // ambient
-        let ambient_light = material.colour.scale(material.ambient);
+        let ambient_light = colour.scale(material.ambient);
 
         let incident_reversed = direction.reverse();
         let mut light_contributions = Colour::BLACK;
         for light in self.lights.iter() {
             // diffuse
-            let light_direction = light.direction_from(&hit.normal.from);
-            let diffuse_factor = light_direction.dot(&hit.normal.direction);
+            let light_direction = light.direction_from(&hit.point);
+            let diffuse_factor = normal.dot(&light_direction);
             if diffuse_factor < 0.0 {
                 continue;
             }
-            light_contributions += material.colour.scale(material.diffuse * diffuse_factor);
+            if light.casts_shadows && self.is_shadowed(hit, &normal, light, light_direction.clone())
+            {
+                continue;
+            }
+            light_contributions += colour.scale(material.diffuse * diffuse_factor);
             // specular
-            let reflected_light = light_direction.reflect(&hit.normal.direction);
-            let specular_factor = incident_reversed.dot(&reflected_light);
+            let specular_factor =
+                material
+                    .specular_model
+                    .factor(&light_direction, &normal, &incident_reversed);
             if specular_factor < 0.0 {
                 continue;
             }
-            light_contributions += light.colour.scale(material.specular * specular_factor);
+            light_contributions += light
+                .colour
+                .scale(material.specular * specular_factor.powf(material.shininess));
+        }
+        let local_colour =
+            ambient_light + light_contributions.scale(1.0 / self.lights.len() as f64);
+
+        // without any bounces left, the surface keeps its full local colour rather than
+        // darkening by the weight of light it cannot trace
+        if depth == 0 || (material.reflectivity <= 0.0 && material.transparency <= 0.0) {
+            return local_colour;
         }
-        ambient_light + light_contributions.scale(1.0 / self.lights.len() as f64)
+
+        // split the transmitted light between reflection and refraction
+        let (n1, n2) = if entering {
+            (1.0, material.refractive_index)
+        } else {
+            (material.refractive_index, 1.0)
+        };
+        let mut reflection_weight = material.reflectivity;
+        let mut refraction_weight = 0.0;
+        let mut refracted_direction = None;
+        if material.transparency > 0.0 {
+            match direction.refract(&normal, n1 / n2) {
+                Some(refracted) => {
+                    let reflectance = schlick_reflectance(direction, &normal, &refracted, n1, n2);
+                    reflection_weight += material.transparency * reflectance;
+                    refraction_weight = material.transparency * (1.0 - reflectance);
+                    refracted_direction = Some(refracted);
+                }
+                None => reflection_weight += material.transparency, // total internal reflection
+            }
+        }
+
+        let reflection = if reflection_weight <= 0.0 {
+            Colour::BLACK
+        } else {
+            let reflected = direction.reflect(&normal).normalized();
+            self.trace(&offset_ray(hit, &normal, reflected), depth - 1)
+        };
+        let refraction = match refracted_direction {
+            Some(refracted) => {
+                self.trace(&offset_ray(hit, &normal.reverse(), refracted), depth - 1)
+            }
+            None => Colour::BLACK,
+        };
+        // a material reflecting and transmitting more than all of the light has none left over
+        let local_weight = (1.0 - material.reflectivity - material.transparency).max(0.0);
+        local_colour.scale(local_weight)
+            + reflection.scale(reflection_weight)
+            + refraction.scale(refraction_weight)
+    }
+
+    fn is_shadowed(&self, hit: &Hit, normal: &Normal, light: &Light, direction: Direction) -> bool {
+        let shadow_ray = offset_ray(hit, normal, direction);
+        let distance = light.distance_from(&shadow_ray.from);
+        self.objects.any_intersection(&shadow_ray, distance)
+    }
+}
+
+/// Start a secondary ray at the hit point, moved off the surface along `side`
+fn offset_ray(hit: &Hit, side: &Normal, direction: Direction) -> Ray {
+    Ray {
+        from: hit.point.clone() + side.to_direction().scale(SURFACE_OFFSET),
+        direction,
     }
 }
+
+/// Schlick's approximation of the Fresnel reflectance between media n1 and n2
+fn schlick_reflectance(
+    incident: &Direction,
+    normal: &Normal,
+    refracted: &Direction,
+    n1: f64,
+    n2: f64,
+) -> f64 {
+    let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    // use the angle on the optically less dense side
+    let cosine = if n1 <= n2 {
+        -normal.dot(incident)
+    } else {
+        -normal.dot(refracted)
+    };
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+}
+
+#[cfg(test)]
+fn test_sphere(position: [f64; 3], material: object::material::Material) -> Object {
+    use object::{
+        matrix::{rotation::Orientation, AffineTransformation},
+        ObjectShape,
+    };
+    Object::new(
+        ObjectShape::Sphere,
+        Some(AffineTransformation {
+            scale: [1.0; 3],
+            position,
+            orientation: Orientation::IDENTITY,
+        }),
+        Some(material),
+    )
+}
+
+#[test]
+fn test_reflection() {
+    use object::material::Material;
+    // lit by ambient light alone, so each surface shows exactly its colour
+    let plain = |red: f64, blue: f64, reflectivity: f64| Material {
+        reflectivity,
+        ..Material::new(
+            1.0,
+            0.0,
+            0.0,
+            1.0,
+            Colour {
+                red,
+                green: 0.0,
+                blue,
+            },
+        )
+    };
+    // a blue mirror ahead, reflecting a red sphere behind the eye
+    let scene = |reflectivity: f64| {
+        Scene::new(
+            vec![
+                test_sphere([0.0, 0.0, -5.0], plain(0.0, 1.0, reflectivity)),
+                test_sphere([0.0, 0.0, 5.0], plain(1.0, 0.0, 0.0)),
+            ],
+            vec![Light::new([0.0, 10.0, 0.0])],
+        )
+    };
+    let ray = Ray::new(
+        crate::vector::Vector3::new([0.0; 3]),
+        crate::vector::Vector3::new([0.0, 0.0, -1.0]),
+    );
+    let close = |colour: Colour, [red, blue]: [f64; 2]| {
+        (colour.red - red).abs() < 1e-12
+            && colour.green == 0.0
+            && (colour.blue - blue).abs() < 1e-12
+    };
+    assert!(close(scene(1.0).trace(&ray, 1), [1.0, 0.0]));
+    assert!(close(scene(0.25).trace(&ray, 1), [0.25, 0.75]));
+    // with no bounces left, the mirror keeps its own colour at full strength
+    assert!(close(scene(1.0).trace(&ray, 0), [0.0, 1.0]));
+    assert!(close(scene(0.25).trace(&ray, 0), [0.0, 1.0]));
+}
+
+#[test]
+fn test_schlick_reflectance() {
+    let normal = Normal::new([0.0, 1.0, 0.0]);
+    let down = Direction::new([0.0, -1.0, 0.0]);
+    // head on, the reflectance of glass is ((n1 - n2) / (n1 + n2))^2
+    let reflectance = schlick_reflectance(&down, &normal, &down, 1.0, 1.5);
+    assert!((reflectance - 0.04).abs() < 1e-12);
+    // and all of the light at a grazing angle
+    let grazing = Direction::new([1.0, 0.0, 0.0]);
+    assert!((schlick_reflectance(&grazing, &normal, &down, 1.0, 1.5) - 1.0).abs() < 1e-12);
+    // leaving the glass, the angle of the refracted ray is used
+    let refracted = Direction::new([1.0, -1.0, 0.0]).normalized();
+    let expected = 0.04 + 0.96 * (1.0 - 0.5f64.sqrt()).powi(5);
+    let reflectance = schlick_reflectance(&down, &normal, &refracted, 1.5, 1.0);
+    assert!((reflectance - expected).abs() < 1e-12);
+}
+
+#[test]
+fn test_transparency() {
+    use object::material::Material;
+    let glass = |transparency: f64, reflectivity: f64| Material {
+        transparency,
+        reflectivity,
+        refractive_index: 1.5,
+        ..Material::new(
+            1.0,
+            0.0,
+            0.0,
+            1.0,
+            Colour {
+                red: 0.0,
+                green: 0.0,
+                blue: 1.0,
+            },
+        )
+    };
+    let red = Material::new(
+        1.0,
+        0.0,
+        0.0,
+        1.0,
+        Colour {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+        },
+    );
+    let scene = |glass: Material| {
+        Scene::new(
+            vec![
+                test_sphere([0.0, 0.0, -5.0], glass),
+                test_sphere([0.0, 0.0, -10.0], red.clone()),
+            ],
+            vec![Light::new([0.0, 10.0, 0.0])],
+        )
+    };
+    let ray = |from: [f64; 3]| {
+        Ray::new(
+            crate::vector::Vector3::new(from),
+            crate::vector::Vector3::new([0.0, 0.0, -1.0]),
+        )
+    };
+    let close = |colour: Colour, [red, blue]: [f64; 2]| {
+        (colour.red - red).abs() < 1e-9 && colour.green == 0.0 && (colour.blue - blue).abs() < 1e-9
+    };
+    // head on through clear glass, the red sphere behind shows through both surfaces, and
+    // the glass where the light reflected off the back surface runs out of bounces
+    let colour = scene(glass(1.0, 0.0)).trace(&ray([0.0; 3]), 2);
+    assert!(close(colour, [0.96 * 0.96, 0.96 * 0.04]));
+
+    // steeply from within, all of the light reflects off the inside of the surface
+    let colour = scene(glass(1.0, 0.0)).trace(&ray([0.0, 0.9, -5.0]), 1);
+    assert!(close(colour, [0.0, 1.0]));
+
+    // reflecting and transmitting more than all of the light takes none away, leaving the
+    // refracted light alone
+    let colour = scene(glass(0.9, 0.9)).trace(&ray([0.0; 3]), 1);
+    assert!(close(colour, [0.0, 0.9 * 0.96]));
+}
+
+#[test]
+fn test_shadows() {
+    use object::material::Material;
+    // lit by diffuse light alone, from a light straight back towards the eye
+    let diffuse = Material::new(0.0, 1.0, 0.0, 1.0, Colour::WHITE);
+    let scene = |occluder: Option<([f64; 3], bool)>| {
+        let mut objects = vec![test_sphere([0.0, 0.0, -5.0], diffuse.clone())];
+        if let Some((position, casts_shadows)) = occluder {
+            let mut object = test_sphere(position, diffuse.clone());
+            object.casts_shadows = casts_shadows;
+            objects.push(object);
+        }
+        Scene::new(objects, vec![Light::new([0.0, 0.0, 5.0])])
+    };
+    let ray = Ray::new(
+        crate::vector::Vector3::new([0.0; 3]),
+        crate::vector::Vector3::new([0.0, 0.0, -1.0]),
+    );
+    let brightness = |scene: Scene| scene.trace(&ray, 0).red;
+    assert_eq!(brightness(scene(None)), 1.0);
+    // between the surface and the light, beside the eye
+    assert_eq!(brightness(scene(Some(([0.0, 0.0, 2.5], true)))), 0.0);
+    // beyond the light
+    assert_eq!(brightness(scene(Some(([0.0, 0.0, 8.0], true)))), 1.0);
+    assert_eq!(brightness(scene(Some(([0.0, 0.0, 2.5], false)))), 1.0);
+}
@@ -1,26 +1,35 @@
 use ndarray::array;
 
-use crate::{image::Colour, vector::HVector};
+use crate::{
+    image::Colour,
+    vector::{Direction, Point},
+};
 enum LightShape {
     PointLight,
 }
 
 pub struct Light {
     shape: LightShape,
-    location: HVector,
+    location: Point,
     pub colour: Colour,
+    pub casts_shadows: bool,
 }
 
 impl Light {
     pub fn new(point: [f64; 3]) -> Light {
         Light {
             shape: LightShape::PointLight,
-            location: HVector::new([point[0], point[1], -point[2]]),
+            location: Point::new(point),
             colour: Colour::WHITE,
+            casts_shadows: true,
         }
     }
 
-    pub fn direction_from(&self, point: &HVector) -> HVector {
+    pub fn direction_from(&self, point: &Point) -> Direction {
         (self.location.clone() - point.clone()).normalized()
     }
+
+    pub fn distance_from(&self, point: &Point) -> f64 {
+        (self.location.clone() - point.clone()).magnitude()
+    }
 }
@@ -1,18 +1,27 @@
 use crate::{
     ray::{Hit, Ray},
-    vector::HVector,
+    vector::{Normal, Point},
 };
-use std::f64::consts::PI;
+
+pub mod bvh;
+use bvh::{BoundingBox, BoundingVolumeHierarchy};
 
 pub mod intersection;
-use intersection::find_closest_intersection;
 
-mod parsers;
-//use parsers::*;
+pub mod parsers;
 
 pub mod material;
 use material::Material;
 
+pub mod primitives;
+use primitives::{Crossing, Quadric};
+
+pub mod csg;
+use csg::Csg;
+
+pub mod distance_field;
+use distance_field::DistanceField;
+
 pub mod matrix;
 use matrix::{AffineMatrix, AffineTransformation};
 
@@ -20,123 +29,289 @@ use matrix::{AffineMatrix, AffineTransformation};
 //fn get_points(&self) -> [HVector; 3];
 trait LeafShape {
     fn intersection<'a>(&'a self, ray: &Ray) -> Option<Hit<'a>>; // TODO: no material on leaf hits
-    fn get_normal(&self, u: f64, v: f64) -> HVector;
+    fn get_normal(&self, u: f64, v: f64) -> Normal;
     fn get_texture_coordinates(&self, u: f64, v: f64) -> [f64; 2];
 }
 
 pub struct MappedTrianglePoint {
-    point: HVector,
+    point: Point,
     texture_coordinates: [f64; 2],
-    normal: HVector,
+    normal: Normal,
+}
+
+impl MappedTrianglePoint {
+    pub fn new(point: Point, texture_coordinates: [f64; 2], normal: Normal) -> MappedTrianglePoint {
+        MappedTrianglePoint {
+            point,
+            texture_coordinates,
+            normal,
+        }
+    }
+}
+
+/// Weights of the three corners of a triangle at barycentric coordinates u and v, which
+/// interpolate a property given at each corner
+fn barycentric_weights(u: f64, v: f64) -> [f64; 3] {
+    [1.0 - u - v, u, v]
+}
+
+/// A triangle whose normal and texture coordinates are interpolated between its corners
+pub struct MappedTriangle([MappedTrianglePoint; 3]);
+
+impl MappedTriangle {
+    pub fn new(
+        p1: MappedTrianglePoint,
+        p2: MappedTrianglePoint,
+        p3: MappedTrianglePoint,
+    ) -> MappedTriangle {
+        MappedTriangle([p1, p2, p3])
+    }
+}
+
+impl LeafShape for MappedTriangle {
+    fn intersection<'a>(&'a self, ray: &Ray) -> Option<Hit<'a>> {
+        let [p1, p2, p3] = &self.0;
+        let (distance, u, v) = triangle_intersection([&p1.point, &p2.point, &p3.point], ray)?;
+        let hit_point = ray.from.clone() + ray.direction.scale(distance);
+        Some(Hit {
+            point: hit_point.clone(),
+            normal: self.get_normal(u, v),
+            object_point: hit_point,
+            texture_coordinates: self.get_texture_coordinates(u, v),
+            material: None,
+        })
+    }
+
+    fn get_normal(&self, u: f64, v: f64) -> Normal {
+        let weights = barycentric_weights(u, v);
+        let mut result = [0.0; 3];
+        for (point, weight) in self.0.iter().zip(weights) {
+            let normal = point.normal.to_array();
+            for i in 0..3 {
+                result[i] += normal[i] * weight;
+            }
+        }
+        Normal::new(result).normalized()
+    }
+
+    fn get_texture_coordinates(&self, u: f64, v: f64) -> [f64; 2] {
+        let weights = barycentric_weights(u, v);
+        let mut result = [0.0; 2];
+        for (point, weight) in self.0.iter().zip(weights) {
+            result[0] += point.texture_coordinates[0] * weight;
+            result[1] += point.texture_coordinates[1] * weight;
+        }
+        result
+    }
 }
 
 pub enum ObjectShape {
     Sphere,
-    Triangle(HVector, HVector, HVector),
-    GroupedMesh(Vec<TexturedObject>),
-    Mesh(Vec<LeafObject>),
+    /// Infinite, through the origin and facing +y
+    Plane,
+    /// Of radius 1, through the origin and facing +y
+    Disk,
+    /// From -1 to 1 on every axis
+    Cuboid,
+    /// Of radius 1 around the y axis, from -1 to 1, optionally closed at the ends
+    Cylinder {
+        capped: bool,
+    },
+    /// With its apex at y = 1 and a base of radius 1 at y = -1, optionally closed at the base
+    Cone {
+        capped: bool,
+    },
+    /// Around the y axis, with its tube's centre 1 from the axis
+    Torus {
+        minor_radius: f64,
+    },
+    Quadric(Quadric),
+    /// Two solids combined by a union, intersection or difference
+    Csg(Csg),
+    /// A signed distance function, traced by stepping along rays
+    DistanceField(DistanceField),
+    Triangle(Point, Point, Point),
+    MappedTriangle(MappedTriangle),
+    GroupedMesh(BoundingVolumeHierarchy<TexturedObject>),
+    Mesh(BoundingVolumeHierarchy<LeafObject>),
 }
 use ObjectShape::*;
 
 const EPSILON: f64 = 1e-20;
 
+/// Distance along the ray and barycentric coordinates u and v of its hit on the front of a
+/// triangle, using the Moeller-Trumbore intersection algorithm
+fn triangle_intersection([p1, p2, p3]: [&Point; 3], ray: &Ray) -> Option<(f64, f64, f64)> {
+    let side1 = p2.clone() - p1.clone();
+    let side2 = p3.clone() - p1.clone();
+    let p_vector = ray.direction.cross(&side2);
+
+    let determinant = side1.dot(&p_vector);
+    if determinant <= EPSILON {
+        // TODO: if double-sided, check absolute value
+        return None; // parallel to or hit wrong side of triangle
+    }
+    let inverse_determinant = 1.0 / determinant;
+
+    let t_vector = ray.from.clone() - p1.clone();
+    let u = t_vector.dot(&p_vector) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None; // barycentric coordinates not in triangle
+    }
+
+    let q_vector = t_vector.cross(&side1);
+    let v = ray.direction.dot(&q_vector) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None; // barycentric coordinates not in triangle
+    }
+
+    let distance = side2.dot(&q_vector) * inverse_determinant;
+    if distance <= EPSILON {
+        return None; // hit object too close or backwards
+    }
+    Some((distance, u, v))
+}
+
+fn to_hit(crossing: Crossing, ray: &Ray) -> Hit<'static> {
+    let hit_point = ray.from.clone() + ray.direction.scale(crossing.distance);
+    Hit {
+        point: hit_point.clone(),
+        normal: crossing.normal,
+        object_point: hit_point,
+        texture_coordinates: crossing.texture_coordinates,
+        material: None,
+    }
+}
+
+/// Hit at the first crossing in front of the ray
+fn first_hit(crossings: Vec<Crossing>, ray: &Ray) -> Option<Hit<'static>> {
+    let crossing = crossings
+        .into_iter()
+        .find(|crossing| crossing.distance > EPSILON)?;
+    Some(to_hit(crossing, ray))
+}
+
 impl ObjectShape {
     fn intersection<'a>(&'a self, ray: &Ray) -> Option<Hit<'a>> {
         let material = None;
         match self {
-            Sphere => {
-                // ray = from + k * direction
-                // sphere = x^2 + y^2 + z^2 = 1
-                //      1 = ray . ray
-                //        = (F + kD) . (F + kD)
-                //        = (D.D) * k^2     + (2*D.F) * k       + (F.F - 1)
-                //        =   a   * k^2     +  2 * b  * k       +     c
-                // a = D.D = 1
-                // b = D.F
-                // c = F.F
-                // d = b^2 - c
-                // k = (-B +/- sqrt(d))
-                let b = ray.direction.dot(&ray.from);
-                let c = ray.from.dot(&ray.from) - 1.0;
-                let d = b * b - c;
-                if d < 0.0 {
-                    return None; // no intersection
-                }
-
-                let k = {
-                    let k1 = -b + d.sqrt();
-                    let k2 = -b - d.sqrt();
-                    if k2 <= EPSILON {
-                        k1
-                    } else {
-                        k2
-                    }
-                };
-                if k <= EPSILON {
-                    return None; // hit object too close or backwards
-                }
-
-                let hit_point = ray.from.clone() + ray.direction.scale(k);
-                let normal = Ray {
-                    from: hit_point.clone(),
-                    direction: hit_point.clone(),
-                };
-                let [x, y, z] = hit_point.to_array();
-                let texture_coordinates = [0.5 + x.atan2(z) / (2.0 * PI), 0.5 + y.asin() / PI];
-
-                Some(Hit {
-                    normal,
-                    texture_coordinates,
-                    material,
-                })
-            }
+            Sphere => first_hit(primitives::sphere(ray), ray),
             Triangle(p1, p2, p3) => {
-                // Moeller-Trombore intersection algorithm
-                let side1 = p2.clone() - p1.clone();
-                let side2 = p3.clone() - p1.clone();
-                let p_vector = ray.direction.cross(&side2);
-
-                let determinant = side1.dot(&p_vector);
-                if determinant <= EPSILON {
-                    // TODO: if double-sided, check absolute value
-                    return None; // parallel to or hit wrong side of triangle
-                }
-                let inverse_determinant = 1.0 / determinant;
-
-                let t_vector = ray.from.clone() - p1.clone();
-                let u = t_vector.dot(&p_vector) * inverse_determinant;
-                if u < 0.0 || u > 1.0 {
-                    return None; // barycentric coordinates not in triangle
-                }
-
-                let q_vector = t_vector.cross(&side1);
-                let v = ray.direction.dot(&q_vector) * inverse_determinant;
-                if v < 0.0 || u + v > 1.0 {
-                    return None; // barycentric coordinates not in triangle
-                }
-
-                let distance = side2.dot(&q_vector) * inverse_determinant;
-                if distance <= EPSILON {
-                    return None; // hit object too close or backwards
-                }
+                let (distance, u, v) = triangle_intersection([p1, p2, p3], ray)?;
 
                 // Compute and return Hit
-                let plane_normal = side1.cross(&side2);
+                let plane_normal = (p2.clone() - p1.clone()).cross(&(p3.clone() - p1.clone()));
                 let hit_point = ray.from.clone() + ray.direction.scale(distance);
-                let normal = Ray {
-                    from: hit_point,
-                    direction: plane_normal,
-                };
+                let normal = Normal::new(plane_normal.to_array());
                 let texture_coordinates = [u, v];
 
                 Some(Hit {
+                    point: hit_point.clone(),
                     normal,
+                    object_point: hit_point,
                     texture_coordinates,
                     material,
                 })
             }
-            GroupedMesh(children) => find_closest_intersection(children, ray),
-            Mesh(children) => find_closest_intersection(children, ray),
+            Plane => first_hit(primitives::plane(ray), ray),
+            Disk => first_hit(primitives::disk(ray), ray),
+            Cuboid => first_hit(primitives::cuboid(ray), ray),
+            Cylinder { capped } => first_hit(primitives::cylinder(ray, *capped), ray),
+            Cone { capped } => first_hit(primitives::cone(ray, *capped), ray),
+            Torus { minor_radius } => first_hit(primitives::torus(ray, *minor_radius), ray),
+            ObjectShape::Quadric(quadric) => first_hit(quadric.crossings(ray), ray),
+            ObjectShape::DistanceField(field) => {
+                first_hit(field.crossing(ray).into_iter().collect(), ray)
+            }
+            ObjectShape::Csg(csg) => csg
+                .crossings(ray)
+                .into_iter()
+                .find(|hit| (hit.point.clone() - ray.from.clone()).dot(&ray.direction) > EPSILON),
+            ObjectShape::MappedTriangle(triangle) => triangle.intersection(ray),
+            GroupedMesh(children) => children.closest_intersection(ray),
+            Mesh(children) => children.closest_intersection(ray),
+        }
+    }
+
+    /// Whether the shape encloses a volume, which rays alternately enter and leave
+    fn is_solid(&self) -> bool {
+        matches!(
+            self,
+            Sphere
+                | Plane
+                | Cuboid
+                | Cylinder { capped: true }
+                | Cone { capped: true }
+                | Torus { .. }
+                | ObjectShape::DistanceField(_)
+                | ObjectShape::Csg(_)
+        )
+    }
+
+    /// Whether a point in object space is inside a solid; false for any other shape
+    fn contains(&self, point: &Point) -> bool {
+        let [x, y, z] = point.to_array();
+        match self {
+            Sphere => x * x + y * y + z * z < 1.0,
+            Plane => y < 0.0,
+            Cuboid => [x, y, z].iter().all(|c| c.abs() < 1.0),
+            Cylinder { capped: true } => x * x + z * z < 1.0 && y.abs() < 1.0,
+            Cone { capped: true } => x * x + z * z < ((1.0 - y) / 2.0).powi(2) && y.abs() < 1.0,
+            Torus { minor_radius } => {
+                let k = x * x + y * y + z * z + 1.0 - minor_radius * minor_radius;
+                k * k < 4.0 * (x * x + z * z)
+            }
+            ObjectShape::DistanceField(field) => field.distance([x, y, z]) < 0.0,
+            ObjectShape::Csg(csg) => csg.contains(point),
+            _ => false,
+        }
+    }
+
+    /// Every hit on the surface of a solid along the whole line of the ray, behind its start
+    /// as well as in front, in order; None if the shape is not solid
+    fn crossings<'a>(&'a self, ray: &Ray) -> Option<Vec<Hit<'a>>> {
+        let crossings = match self {
+            Sphere => primitives::sphere(ray),
+            Plane => primitives::plane(ray),
+            Cuboid => primitives::cuboid(ray),
+            Cylinder { capped: true } => primitives::cylinder(ray, true),
+            Cone { capped: true } => primitives::cone(ray, true),
+            Torus { minor_radius } => primitives::torus(ray, *minor_radius),
+            ObjectShape::DistanceField(field) => field.crossings(ray),
+            ObjectShape::Csg(csg) => return Some(csg.crossings(ray)),
+            _ => return None,
+        };
+        Some(
+            crossings
+                .into_iter()
+                .map(|crossing| to_hit(crossing, ray))
+                .collect(),
+        )
+    }
+
+    /// Bounds in object space, or None if the shape is unbounded
+    fn bounds(&self) -> Option<BoundingBox> {
+        match self {
+            Sphere | Cuboid | Cylinder { .. } | Cone { .. } => {
+                Some(BoundingBox::new([-1.0; 3], [1.0; 3]))
+            }
+            Torus { minor_radius } => {
+                let (outer, r) = (1.0 + minor_radius, *minor_radius);
+                Some(BoundingBox::new([-outer, -r, -outer], [outer, r, outer]))
+            }
+            ObjectShape::Quadric(_) => Some(BoundingBox::new([-1.0; 3], [1.0; 3])),
+            ObjectShape::Csg(csg) => csg.bounds(),
+            ObjectShape::DistanceField(field) => field.bounds(),
+            Plane => None,
+            Disk => Some(BoundingBox::new([-1.0, 0.0, -1.0], [1.0, 0.0, 1.0])),
+            Triangle(p1, p2, p3) => Some(BoundingBox::from_points(
+                [p1, p2, p3].iter().map(|point| point.to_array()),
+            )),
+            ObjectShape::MappedTriangle(triangle) => Some(BoundingBox::from_points(
+                triangle.0.iter().map(|corner| corner.point.to_array()),
+            )),
+            GroupedMesh(children) => children.bounds(),
+            Mesh(children) => children.bounds(),
         }
     }
 }
@@ -146,11 +321,17 @@ pub struct Object {
     material: Material,
     matrix: AffineMatrix,
     shape: ObjectShape,
+    pub casts_shadows: bool,
 }
 
 pub trait Intersectable {
-    fn intersect(&self, ray: &Ray) -> Option<Hit>;
+    fn intersect(&self, ray: &Ray) -> Option<Hit<'_>>;
     fn get_material(&self) -> Option<&Material>;
+    /// Bounds in the space of the incoming rays, or None if unbounded
+    fn bounds(&self) -> Option<BoundingBox>;
+    fn casts_shadows(&self) -> bool {
+        true
+    }
 }
 
 impl Object {
@@ -160,31 +341,67 @@ impl Object {
         material: Option<Material>,
     ) -> Object {
         let matrix = AffineMatrix::new(transformation.unwrap_or_default());
+        Object::with_matrix(shape, matrix, material)
+    }
+
+    /// An object placed by any matrix, such as one made by composing several
+    pub fn with_matrix(
+        shape: ObjectShape,
+        matrix: AffineMatrix,
+        material: Option<Material>,
+    ) -> Object {
         let material: Material = material.unwrap_or_default();
         Object {
             shape,
             matrix,
             material,
+            casts_shadows: true,
         }
     }
+
+    /// Whether a point is inside the object's solid shape
+    fn contains(&self, point: &Point) -> bool {
+        self.shape.contains(&self.matrix.shift_point(point))
+    }
+
+    /// Every hit on the surface of the object along the whole line of the ray, in order, or
+    /// None if its shape is not solid
+    fn crossings(&self, ray: &Ray) -> Option<Vec<Hit<'_>>> {
+        let hits = self.shape.crossings(&self.matrix.shift(ray))?;
+        Some(
+            hits.into_iter()
+                .map(|hit| Hit {
+                    material: hit.material.or(self.get_material()),
+                    ..self.matrix.unshift_hit(hit)
+                })
+                .collect(),
+        )
+    }
 }
 
 impl Intersectable for Object {
-    fn intersect(&self, ray: &Ray) -> Option<Hit> {
+    fn intersect(&self, ray: &Ray) -> Option<Hit<'_>> {
         self.shape
             .intersection(&self.matrix.shift(ray))
-            .and_then(|hit| {
-                Some(Hit {
-                    normal: self.matrix.unshift(&hit.normal),
-                    material: hit.material.or(self.get_material()),
-                    ..hit
-                })
+            .map(|hit| Hit {
+                material: hit.material.or(self.get_material()),
+                ..self.matrix.unshift_hit(hit)
             })
     }
 
     fn get_material(&self) -> Option<&Material> {
         Some(&self.material)
     }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.shape
+            .bounds()
+            .map(|bounds| bounds.transformed(&self.matrix))
+    }
+
+    fn casts_shadows(&self) -> bool {
+        self.casts_shadows
+    }
 }
 
 pub struct ChildObject {
@@ -200,21 +417,24 @@ impl ChildObject {
 }
 
 impl Intersectable for ChildObject {
-    fn intersect(&self, ray: &Ray) -> Option<Hit> {
+    fn intersect(&self, ray: &Ray) -> Option<Hit<'_>> {
         self.shape
             .intersection(&self.matrix.shift(ray))
-            .and_then(|hit| {
-                Some(Hit {
-                    normal: self.matrix.unshift(&hit.normal),
-                    material: hit.material.or(self.get_material()),
-                    ..hit
-                })
+            .map(|hit| Hit {
+                material: hit.material.or(self.get_material()),
+                ..self.matrix.unshift_hit(hit)
             })
     }
 
     fn get_material(&self) -> Option<&Material> {
         None
     }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.shape
+            .bounds()
+            .map(|bounds| bounds.transformed(&self.matrix))
+    }
 }
 
 pub struct LeafObject {
@@ -228,13 +448,17 @@ impl LeafObject {
 }
 
 impl Intersectable for LeafObject {
-    fn intersect(&self, ray: &Ray) -> Option<Hit> {
+    fn intersect(&self, ray: &Ray) -> Option<Hit<'_>> {
         self.shape.intersection(ray)
     }
 
     fn get_material(&self) -> Option<&Material> {
         None
     }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.shape.bounds()
+    }
 }
 
 pub struct TexturedObject {
@@ -249,16 +473,18 @@ impl TexturedObject {
 }
 
 impl Intersectable for TexturedObject {
-    fn intersect(&self, ray: &Ray) -> Option<Hit> {
-        self.shape.intersection(ray).and_then(|hit| {
-            Some(Hit {
-                material: hit.material.or(self.get_material()),
-                ..hit
-            })
+    fn intersect(&self, ray: &Ray) -> Option<Hit<'_>> {
+        self.shape.intersection(ray).map(|hit| Hit {
+            material: hit.material.or(self.get_material()),
+            ..hit
         })
     }
 
     fn get_material(&self) -> Option<&Material> {
         Some(&self.material)
     }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        self.shape.bounds()
+    }
 }
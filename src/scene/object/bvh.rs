@@ -0,0 +1,486 @@
+use crate::{
+    ray::{Hit, Ray},
+    scene::object::{
+        intersection::{find_any_intersection, find_closest_intersection},
+        matrix::AffineMatrix,
+        Intersectable,
+    },
+    vector::Point,
+};
+
+/// Number of buckets centroids are sorted into when searching for the best split
+const BIN_COUNT: usize = 12;
+/// Cost of visiting a node, relative to the cost of intersecting one object
+const TRAVERSAL_COST: f64 = 0.125;
+/// Largest number of objects a leaf may hold when splitting it would not pay off
+const MAX_LEAF_SIZE: usize = 8;
+
+/// An axis-aligned box, given by its smallest and largest corners
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl BoundingBox {
+    pub const EMPTY: BoundingBox = BoundingBox {
+        min: [f64::INFINITY; 3],
+        max: [f64::NEG_INFINITY; 3],
+    };
+
+    pub fn new(min: [f64; 3], max: [f64; 3]) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = [f64; 3]>) -> BoundingBox {
+        points
+            .into_iter()
+            .fold(BoundingBox::EMPTY, |bounds, point| {
+                bounds.union(&BoundingBox::new(point, point))
+            })
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let mut result = *self;
+        for axis in 0..3 {
+            result.min[axis] = self.min[axis].min(other.min[axis]);
+            result.max[axis] = self.max[axis].max(other.max[axis]);
+        }
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|axis| self.min[axis] > self.max[axis])
+    }
+
+    pub fn centroid(&self) -> [f64; 3] {
+        let mut centroid = [0.0; 3];
+        for (axis, coordinate) in centroid.iter_mut().enumerate() {
+            *coordinate = 0.5 * (self.min[axis] + self.max[axis]);
+        }
+        centroid
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let [x, y, z] = [0, 1, 2].map(|axis| self.max[axis] - self.min[axis]);
+        2.0 * (x * y + y * z + z * x)
+    }
+
+    pub fn corners(&self) -> [[f64; 3]; 8] {
+        let [x0, y0, z0] = self.min;
+        let [x1, y1, z1] = self.max;
+        [
+            [x0, y0, z0],
+            [x0, y0, z1],
+            [x0, y1, z0],
+            [x0, y1, z1],
+            [x1, y0, z0],
+            [x1, y0, z1],
+            [x1, y1, z0],
+            [x1, y1, z1],
+        ]
+    }
+
+    /// The box enclosing this one after it is moved out of the object space of `matrix`
+    pub fn transformed(&self, matrix: &AffineMatrix) -> BoundingBox {
+        if self.is_empty() {
+            return *self;
+        }
+        BoundingBox::from_points(
+            self.corners()
+                .iter()
+                .map(|&corner| matrix.unshift_point(&Point::new(corner)).to_array()),
+        )
+    }
+
+    /// Distance along the ray at which it enters the box (0 if it starts inside),
+    /// or None if the box is missed or only entered beyond `max_distance`
+    fn entry_distance(
+        &self,
+        ray: &Ray,
+        inverse_direction: &[f64; 3],
+        max_distance: f64,
+    ) -> Option<f64> {
+        self.range(ray, inverse_direction, max_distance)
+            .map(|(near, _)| near)
+    }
+
+    /// Distances along the ray between which it is inside the box, counting from 0 if it
+    /// starts inside, or None if it misses the box
+    pub fn ray_range(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let inverse_direction = ray.direction.to_array().map(|component| 1.0 / component);
+        self.range(ray, &inverse_direction, f64::INFINITY)
+    }
+
+    /// The part of [0, max_distance] along the ray inside the box, if any
+    fn range(
+        &self,
+        ray: &Ray,
+        inverse_direction: &[f64; 3],
+        max_distance: f64,
+    ) -> Option<(f64, f64)> {
+        let from = ray.from.to_array();
+        let mut near: f64 = 0.0;
+        let mut far = max_distance;
+        for axis in 0..3 {
+            let t1 = (self.min[axis] - from[axis]) * inverse_direction[axis];
+            let t2 = (self.max[axis] - from[axis]) * inverse_direction[axis];
+            // min/max discard the NaN produced by a ray lying in a slab's boundary plane
+            near = near.max(t1.min(t2));
+            far = far.min(t1.max(t2));
+        }
+        if near <= far {
+            Some((near, far))
+        } else {
+            None
+        }
+    }
+}
+
+enum Node {
+    Leaf {
+        bounds: BoundingBox,
+        start: usize,
+        count: usize,
+    },
+    Branch {
+        bounds: BoundingBox,
+        axis: usize,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// A bounding volume hierarchy over a list of objects, split using the surface area
+/// heuristic. Objects without bounds (see `Intersectable::bounds`) are tested on every ray.
+pub struct BoundingVolumeHierarchy<O> {
+    /// Bounded objects in leaf order, followed by the unbounded ones
+    objects: Vec<O>,
+    bounded_count: usize,
+    nodes: Vec<Node>,
+}
+
+impl<O: Intersectable> BoundingVolumeHierarchy<O> {
+    pub fn new(objects: Vec<O>) -> BoundingVolumeHierarchy<O> {
+        let all_bounds: Vec<Option<BoundingBox>> =
+            objects.iter().map(|object| object.bounds()).collect();
+        let mut order: Vec<usize> = (0..objects.len())
+            .filter(|&i| all_bounds[i].is_some())
+            .collect();
+        let bounded_count = order.len();
+        let bounds: Vec<BoundingBox> = all_bounds
+            .iter()
+            .map(|bounds| bounds.unwrap_or(BoundingBox::EMPTY))
+            .collect();
+        let centroids: Vec<[f64; 3]> = bounds.iter().map(BoundingBox::centroid).collect();
+
+        let mut nodes = vec![];
+        if bounded_count > 0 {
+            build(&mut nodes, &mut order, 0, &bounds, &centroids);
+        }
+        order.extend((0..objects.len()).filter(|&i| all_bounds[i].is_none()));
+
+        let mut slots: Vec<Option<O>> = objects.into_iter().map(Some).collect();
+        let objects = order
+            .iter()
+            .map(|&i| slots[i].take().unwrap()) // each index appears exactly once
+            .collect();
+        BoundingVolumeHierarchy {
+            objects,
+            bounded_count,
+            nodes,
+        }
+    }
+
+    /// Bounds of all objects, or None if any of them is unbounded
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        if self.bounded_count < self.objects.len() {
+            return None;
+        }
+        Some(
+            self.nodes
+                .first()
+                .map_or(BoundingBox::EMPTY, |root| *root.bounds()),
+        )
+    }
+
+    pub fn closest_intersection(&self, ray: &Ray) -> Option<Hit<'_>> {
+        let mut best = find_closest_intersection(&self.objects[self.bounded_count..], ray)
+            .map(|hit| (hit_distance(ray, &hit), hit));
+        let max_distance = best
+            .as_ref()
+            .map_or(f64::INFINITY, |(distance, _)| *distance);
+        self.traverse(ray, max_distance, |objects, max_distance| {
+            if let Some(hit) = find_closest_intersection(objects, ray) {
+                let distance = hit_distance(ray, &hit);
+                if distance < max_distance {
+                    best = Some((distance, hit));
+                }
+            }
+            best.as_ref()
+                .map_or(f64::INFINITY, |(distance, _)| *distance)
+        });
+        best.map(|(_, hit)| hit)
+    }
+
+    /// Whether any shadow-casting object is hit closer than `max_distance` along the ray
+    pub fn any_intersection(&self, ray: &Ray, max_distance: f64) -> bool {
+        if find_any_intersection(&self.objects[self.bounded_count..], ray, max_distance) {
+            return true;
+        }
+        let mut found = false;
+        self.traverse(ray, max_distance, |objects, max_distance| {
+            found = find_any_intersection(objects, ray, max_distance);
+            // a negative limit culls every remaining node
+            if found {
+                -1.0
+            } else {
+                max_distance
+            }
+        });
+        found
+    }
+
+    /// Visit the leaves whose boxes the ray enters closer than the current limit, nearest
+    /// first where cheap to tell; `visit` returns the new limit after testing a leaf
+    fn traverse<'a, F>(&'a self, ray: &Ray, mut max_distance: f64, mut visit: F)
+    where
+        F: FnMut(&'a [O], f64) -> f64,
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let direction = ray.direction.to_array();
+        let inverse_direction = direction.map(|component| 1.0 / component);
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node
+                .bounds()
+                .entry_distance(ray, &inverse_direction, max_distance)
+                .is_none()
+            {
+                continue;
+            }
+            match *node {
+                Node::Leaf { start, count, .. } => {
+                    max_distance = visit(&self.objects[start..start + count], max_distance);
+                }
+                Node::Branch {
+                    axis, left, right, ..
+                } => {
+                    // push the far child first so the near one is popped next
+                    if direction[axis] < 0.0 {
+                        stack.push(left);
+                        stack.push(right);
+                    } else {
+                        stack.push(right);
+                        stack.push(left);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn hit_distance(ray: &Ray, hit: &Hit) -> f64 {
+    (hit.point.clone() - ray.from.clone()).magnitude()
+}
+
+/// Recursively build the nodes over `items`, which starts at position `offset` in the final
+/// object order, reordering `items` so each leaf covers a contiguous range. Returns the index
+/// of the created node.
+fn build(
+    nodes: &mut Vec<Node>,
+    items: &mut [usize],
+    offset: usize,
+    bounds: &[BoundingBox],
+    centroids: &[[f64; 3]],
+) -> usize {
+    let node_bounds = items
+        .iter()
+        .fold(BoundingBox::EMPTY, |total, &i| total.union(&bounds[i]));
+    let index = nodes.len();
+    nodes.push(Node::Leaf {
+        bounds: node_bounds,
+        start: offset,
+        count: items.len(),
+    });
+    if items.len() <= 1 {
+        return index;
+    }
+
+    let centroid_bounds = BoundingBox::from_points(items.iter().map(|&i| centroids[i]));
+    let split = find_split(items, &node_bounds, &centroid_bounds, bounds, centroids);
+    let (axis, left_count) = match split {
+        Some((axis, bin)) => {
+            let left_count = partition(items, |i| {
+                bin_index(&centroid_bounds, centroids[i], axis) <= bin
+            });
+            (axis, left_count)
+        }
+        None if items.len() > MAX_LEAF_SIZE => {
+            // no worthwhile split, but too many objects for one leaf: halve along the widest axis
+            let axis = widest_axis(&centroid_bounds);
+            items.sort_by(|&a, &b| centroids[a][axis].total_cmp(&centroids[b][axis]));
+            (axis, items.len() / 2)
+        }
+        None => return index,
+    };
+
+    let (left_items, right_items) = items.split_at_mut(left_count);
+    let left = build(nodes, left_items, offset, bounds, centroids);
+    let right = build(nodes, right_items, offset + left_count, bounds, centroids);
+    nodes[index] = Node::Branch {
+        bounds: node_bounds,
+        axis,
+        left,
+        right,
+    };
+    index
+}
+
+/// The axis and last left bin of the cheapest binned split, if it beats making a leaf
+fn find_split(
+    items: &[usize],
+    node_bounds: &BoundingBox,
+    centroid_bounds: &BoundingBox,
+    bounds: &[BoundingBox],
+    centroids: &[[f64; 3]],
+) -> Option<(usize, usize)> {
+    let leaf_cost = items.len() as f64;
+    let node_area = node_bounds.surface_area();
+    let mut best: Option<(f64, usize, usize)> = None;
+    for axis in 0..3 {
+        if centroid_bounds.max[axis] <= centroid_bounds.min[axis] {
+            continue; // all centroids in one plane, cannot split along this axis
+        }
+        let mut bin_counts = [0usize; BIN_COUNT];
+        let mut bin_bounds = [BoundingBox::EMPTY; BIN_COUNT];
+        for &i in items {
+            let bin = bin_index(centroid_bounds, centroids[i], axis);
+            bin_counts[bin] += 1;
+            bin_bounds[bin] = bin_bounds[bin].union(&bounds[i]);
+        }
+        // sweep from the right to get the cost of everything after each bin
+        let mut right_costs = [0.0; BIN_COUNT];
+        let mut right_bounds = BoundingBox::EMPTY;
+        let mut right_count = 0;
+        for bin in (1..BIN_COUNT).rev() {
+            right_bounds = right_bounds.union(&bin_bounds[bin]);
+            right_count += bin_counts[bin];
+            right_costs[bin - 1] = right_bounds.surface_area() * right_count as f64;
+        }
+        let mut left_bounds = BoundingBox::EMPTY;
+        let mut left_count = 0;
+        for bin in 0..BIN_COUNT - 1 {
+            left_bounds = left_bounds.union(&bin_bounds[bin]);
+            left_count += bin_counts[bin];
+            if left_count == 0 || left_count == items.len() {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (left_bounds.surface_area() * left_count as f64 + right_costs[bin]) / node_area;
+            if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, bin));
+            }
+        }
+    }
+    best.filter(|&(cost, _, _)| cost < leaf_cost)
+        .map(|(_, axis, bin)| (axis, bin))
+}
+
+fn bin_index(centroid_bounds: &BoundingBox, centroid: [f64; 3], axis: usize) -> usize {
+    let extent = centroid_bounds.max[axis] - centroid_bounds.min[axis];
+    let relative = (centroid[axis] - centroid_bounds.min[axis]) / extent;
+    ((relative * BIN_COUNT as f64) as usize).min(BIN_COUNT - 1)
+}
+
+fn widest_axis(bounds: &BoundingBox) -> usize {
+    (0..3)
+        .max_by(|&a, &b| {
+            (bounds.max[a] - bounds.min[a]).total_cmp(&(bounds.max[b] - bounds.min[b]))
+        })
+        .unwrap() // range is not empty
+}
+
+/// Move the items matching `predicate` to the front, returning how many there are
+fn partition<P: Fn(usize) -> bool>(items: &mut [usize], predicate: P) -> usize {
+    let mut left_count = 0;
+    for i in 0..items.len() {
+        if predicate(items[i]) {
+            items.swap(left_count, i);
+            left_count += 1;
+        }
+    }
+    left_count
+}
+
+#[test]
+fn test_closest_intersection_matches_linear_search() {
+    use crate::scene::object::{
+        matrix::{rotation::Orientation, AffineTransformation},
+        Object,
+        ObjectShape::Sphere,
+    };
+    use crate::vector::Direction;
+
+    // deterministic scattering of spheres in a 20 unit cube
+    let mut seed: u64 = 12345;
+    let mut next = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 11) as f64 / (1u64 << 53) as f64 * 20.0 - 10.0
+    };
+    let placements: Vec<(f64, [f64; 3])> = (0..200)
+        .map(|_| (0.1 + (next() + 10.0) / 40.0, [next(), next(), next()]))
+        .collect();
+    let spheres = || -> Vec<Object> {
+        placements
+            .iter()
+            .map(|&(radius, position)| {
+                let transformation = AffineTransformation {
+                    scale: [radius; 3],
+                    position,
+                    orientation: Orientation::IDENTITY,
+                };
+                Object::new(Sphere, Some(transformation), None)
+            })
+            .collect()
+    };
+    let linear = spheres();
+    let hierarchy = BoundingVolumeHierarchy::new(spheres());
+
+    for i in 0..400 {
+        let (angle, spread) = (i as f64 * 0.1, (i % 10) as f64 * 0.06);
+        let ray = Ray {
+            from: Point::new([0.0, 0.0, 15.0]),
+            direction: Direction::new([angle.cos() * spread, angle.sin() * spread, -1.0])
+                .normalized(),
+        };
+        let expected = find_closest_intersection(&linear, &ray).map(|hit| hit.point);
+        let actual = hierarchy.closest_intersection(&ray).map(|hit| hit.point);
+        match (expected, actual) {
+            (None, None) => {}
+            (Some(expected), Some(actual)) => {
+                assert!((expected - actual).magnitude() < 1e-9);
+            }
+            (expected, actual) => panic!(
+                "ray {}: expected hit {}, got hit {}",
+                i,
+                expected.is_some(),
+                actual.is_some()
+            ),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::{
+    ray::{Hit, Ray},
+    scene::object::{bvh::BoundingBox, Intersectable, Object},
+    vector::Point,
+};
+
+/// How constructive solid geometry combines two solids
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /// Everything inside either solid
+    Union,
+    /// Everything inside both solids
+    Intersection,
+    /// Everything inside the first solid but not the second
+    Difference,
+}
+
+impl Operation {
+    fn contains(&self, [left, right]: [bool; 2]) -> bool {
+        match self {
+            Operation::Union => left || right,
+            Operation::Intersection => left && right,
+            Operation::Difference => left && !right,
+        }
+    }
+}
+
+/// A solid made by combining two others, each with its own transformation and material. The
+/// surface of the result keeps the material and texture coordinates of the operand it came
+/// from.
+pub struct Csg {
+    operation: Operation,
+    operands: Box<[Object; 2]>,
+}
+
+impl Csg {
+    /// Panics unless both operands are solid: a sphere, plane, cuboid, torus, capped cylinder
+    /// or cone, distance field, or another combination
+    pub fn new(operation: Operation, left: Object, right: Object) -> Csg {
+        assert!(
+            left.shape.is_solid() && right.shape.is_solid(),
+            "only solids can be combined"
+        );
+        Csg {
+            operation,
+            operands: Box::new([left, right]),
+        }
+    }
+
+    /// Every hit on the surface of the combination along the whole line of the ray, in order
+    pub fn crossings(&self, ray: &Ray) -> Vec<Hit<'_>> {
+        let [left, right] = &*self.operands;
+        let [left, right] = [left, right].map(|operand| {
+            operand
+                .crossings(ray)
+                .unwrap_or_default()
+                .into_iter()
+                .peekable()
+        });
+        let mut hits = [left, right];
+        let distance = |hit: &Hit| (hit.point.clone() - ray.from.clone()).dot(&ray.direction);
+
+        // test a point before the first crossing of each operand, or any point on the line
+        // if it never crosses, as a plane it runs along
+        let mut inside = [0, 1].map(|i| {
+            let before = match hits[i].peek() {
+                Some(hit) => hit.point.clone() + ray.direction.reverse(),
+                None => ray.from.clone(),
+            };
+            self.operands[i].contains(&before)
+        });
+        let mut result = vec![];
+        loop {
+            let next = [0, 1].map(|i| hits[i].peek().map(distance));
+            let operand = match next {
+                [Some(left), Some(right)] => usize::from(right < left),
+                [Some(_), None] => 0,
+                [None, Some(_)] => 1,
+                [None, None] => break,
+            };
+            let hit = hits[operand].next().unwrap();
+            let was_inside = self.operation.contains(inside);
+            inside[operand] = !inside[operand];
+            if self.operation.contains(inside) == was_inside {
+                continue; // within or outside the other solid, so not on the surface
+            }
+            if operand == 1 && self.operation == Operation::Difference {
+                // the inside of the second solid is the outside of the result
+                result.push(Hit {
+                    normal: hit.normal.reverse(),
+                    ..hit
+                });
+            } else {
+                result.push(hit);
+            }
+        }
+        result
+    }
+
+    /// Whether a point is inside the combination
+    pub fn contains(&self, point: &Point) -> bool {
+        let [left, right] = &*self.operands;
+        self.operation
+            .contains([left.contains(point), right.contains(point)])
+    }
+
+    /// Bounds in the space of the combination, or None if it is unbounded
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        let [left, right] = [0, 1].map(|i| self.operands[i].bounds());
+        match (self.operation, left, right) {
+            (Operation::Union, Some(left), Some(right)) => Some(left.union(&right)),
+            (Operation::Union, _, _) => None,
+            (Operation::Intersection, Some(left), Some(right)) => {
+                let mut result = left;
+                for axis in 0..3 {
+                    result.min[axis] = left.min[axis].max(right.min[axis]);
+                    result.max[axis] = left.max[axis].min(right.max[axis]);
+                }
+                Some(result)
+            }
+            (Operation::Intersection, left, right) => left.or(right),
+            (Operation::Difference, left, _) => left,
+        }
+    }
+}
+
+#[test]
+fn test_csg() {
+    use crate::{
+        image::Colour,
+        scene::object::{
+            distance_field::DistanceField,
+            material::Material,
+            matrix::{rotation::Orientation, AffineTransformation},
+            ObjectShape,
+        },
+        vector::{Direction, Point},
+    };
+    let solid = |shape: ObjectShape, position: [f64; 3], red: f64| {
+        Object::new(
+            shape,
+            Some(AffineTransformation {
+                scale: [1.0; 3],
+                position,
+                orientation: Orientation::IDENTITY,
+            }),
+            Some(Material::new(
+                1.0,
+                0.0,
+                0.0,
+                1.0,
+                Colour {
+                    red,
+                    green: 0.0,
+                    blue: 0.0,
+                },
+            )),
+        )
+    };
+    // two unit spheres overlapping between x = 0 and 1
+    let spheres = |operation: Operation| {
+        Csg::new(
+            operation,
+            solid(ObjectShape::Sphere, [0.0; 3], 0.25),
+            solid(ObjectShape::Sphere, [1.0, 0.0, 0.0], 0.75),
+        )
+    };
+    let along_x = Ray {
+        from: Point::new([-5.0, 0.0, 0.0]),
+        direction: Direction::new([1.0, 0.0, 0.0]),
+    };
+    let summary = |csg: &Csg| -> Vec<(f64, f64, f64)> {
+        csg.crossings(&along_x)
+            .iter()
+            .map(|hit| {
+                let red = hit.material.unwrap().get_colour(hit).red;
+                (hit.point.to_array()[0], hit.normal.to_array()[0], red)
+            })
+            .collect()
+    };
+    assert_eq!(
+        summary(&spheres(Operation::Union)),
+        vec![(-1.0, -1.0, 0.25), (2.0, 1.0, 0.75)]
+    );
+    assert_eq!(
+        summary(&spheres(Operation::Intersection)),
+        vec![(0.0, -1.0, 0.75), (1.0, 1.0, 0.25)]
+    );
+    // the inside of the second sphere, facing out of the first
+    assert_eq!(
+        summary(&spheres(Operation::Difference)),
+        vec![(-1.0, -1.0, 0.25), (0.0, 1.0, 0.75)]
+    );
+
+    // a plane parallel to the ray has no crossings, but the ray may still be inside it
+    let half_sphere = |operation: Operation| {
+        Csg::new(
+            operation,
+            solid(ObjectShape::Sphere, [0.0; 3], 0.25),
+            solid(ObjectShape::Plane, [0.0; 3], 0.75),
+        )
+    };
+    let along = |y: f64| Ray {
+        from: Point::new([-5.0, y, 0.0]),
+        direction: Direction::new([1.0, 0.0, 0.0]),
+    };
+    let xs = |csg: &Csg, y: f64| -> Vec<f64> {
+        csg.crossings(&along(y))
+            .iter()
+            .map(|hit| hit.point.to_array()[0])
+            .collect()
+    };
+    let through_sphere = |xs: Vec<f64>| {
+        let edge = 0.75f64.sqrt();
+        xs.len() == 2 && (xs[0] + edge).abs() < 1e-12 && (xs[1] - edge).abs() < 1e-12
+    };
+    assert!(through_sphere(xs(
+        &half_sphere(Operation::Intersection),
+        -0.5
+    )));
+    assert!(xs(&half_sphere(Operation::Intersection), 0.5).is_empty());
+    assert!(xs(&half_sphere(Operation::Difference), -0.5).is_empty());
+    assert!(through_sphere(xs(&half_sphere(Operation::Difference), 0.5)));
+
+    // a distance field takes part like any other solid, if only to within its tracing accuracy
+    let carved = Csg::new(
+        Operation::Difference,
+        solid(ObjectShape::Sphere, [0.0; 3], 0.25),
+        solid(
+            ObjectShape::DistanceField(DistanceField::Sphere { radius: 1.0 }),
+            [1.0, 0.0, 0.0],
+            0.75,
+        ),
+    );
+    let carved = summary(&carved);
+    assert_eq!(carved.len(), 2);
+    assert_eq!(carved[0], (-1.0, -1.0, 0.25));
+    let (x, normal, red) = carved[1];
+    assert!(x.abs() < 1e-4 && (normal - 1.0).abs() < 1e-4 && red == 0.75);
+
+    // starting inside both spheres, the nearest hit leaves their intersection
+    let shape = ObjectShape::Csg(spheres(Operation::Intersection));
+    let hit = shape
+        .intersection(&Ray {
+            from: Point::new([0.5, 0.0, 0.0]),
+            direction: Direction::new([-1.0, 0.0, 0.0]),
+        })
+        .unwrap();
+    assert_eq!(hit.point.to_array(), [0.0, 0.0, 0.0]);
+
+    // nested, and missing the part of the cube taken away
+    let cube_without_sphere = Csg::new(
+        Operation::Difference,
+        solid(ObjectShape::Cuboid, [0.0; 3], 0.5),
+        solid(ObjectShape::Sphere, [0.0, 0.0, 1.0], 0.5),
+    );
+    let nested = Csg::new(
+        Operation::Union,
+        solid(ObjectShape::Csg(cube_without_sphere), [0.0; 3], 0.5),
+        solid(ObjectShape::Sphere, [5.0, 0.0, 0.0], 0.5),
+    );
+    let hits: Vec<[f64; 3]> = nested
+        .crossings(&Ray {
+            from: Point::new([0.0, 0.0, 5.0]),
+            direction: Direction::new([0.0, 0.0, -1.0]),
+        })
+        .iter()
+        .map(|hit| hit.point.to_array())
+        .collect();
+    assert_eq!(hits, vec![[0.0, 0.0, 0.0], [0.0, 0.0, -1.0]]);
+    let bounds = nested.bounds().unwrap();
+    assert_eq!((bounds.min, bounds.max), ([-1.0; 3], [6.0, 1.0, 1.0]));
+}
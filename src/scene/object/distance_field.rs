@@ -0,0 +1,499 @@
+//! Shapes given by signed distance functions, which are negative inside, and rendered by
+//! sphere tracing: stepping along a ray by the distance to the nearest surface, which can
+//! never overshoot it
+use crate::{
+    ray::Ray,
+    scene::object::{
+        bvh::BoundingBox,
+        matrix::{AffineMatrix, AffineTransformation},
+        primitives::Crossing,
+    },
+    vector::{Normal, Point},
+};
+use std::f64::consts::PI;
+
+/// Steps to take along a ray before giving up on it
+const MAX_STEPS: usize = 512;
+/// How close to a surface counts as on it
+const HIT_DISTANCE: f64 = 1e-5;
+/// How far rays are followed through fields without bounds
+const MAX_DISTANCE: f64 = 100.0;
+/// Spacing of the samples used to estimate normals
+const GRADIENT_STEP: f64 = 1e-6;
+
+/// A signed distance function, built up from primitives, each centred on the origin
+pub enum DistanceField {
+    Sphere {
+        radius: f64,
+    },
+    /// Extending `half_size` from the origin along each axis
+    Cuboid {
+        half_size: [f64; 3],
+    },
+    /// A cuboid with its edges and corners rounded off to `radius`, within the same extent
+    RoundBox {
+        half_size: [f64; 3],
+        radius: f64,
+    },
+    /// Around the y axis
+    Torus {
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    /// All points within `radius` of the segment from `start` to `end`
+    Capsule {
+        start: [f64; 3],
+        end: [f64; 3],
+        radius: f64,
+    },
+    /// Either field, blended across a region about `smoothness` wide where they meet, or
+    /// joined sharply if it is 0
+    SmoothUnion {
+        fields: Box<[DistanceField; 2]>,
+        smoothness: f64,
+    },
+    /// The first field with the second carved out of it, blended like a smooth union
+    SmoothSubtraction {
+        fields: Box<[DistanceField; 2]>,
+        smoothness: f64,
+    },
+    /// Copies of a field, `period` apart along each axis, or not repeated along axes where
+    /// the period is 0. Each copy must fit within its cell for distances to stay correct.
+    Repetition {
+        field: Box<DistanceField>,
+        period: [f64; 3],
+    },
+    /// The fractal found by iterating z -> z^power + c on points in spherical coordinates,
+    /// within about 1.2 of the origin
+    Mandelbulb {
+        power: f64,
+        iterations: usize,
+    },
+    /// A field moved out of its own space by a matrix
+    Transformed {
+        field: Box<DistanceField>,
+        matrix: AffineMatrix,
+    },
+}
+
+/// min(a, b), with the corner between them rounded over a width of `smoothness`
+fn smooth_min(a: f64, b: f64, smoothness: f64) -> f64 {
+    if smoothness <= 0.0 {
+        return a.min(b);
+    }
+    let h = (smoothness - (a - b).abs()).max(0.0) / smoothness;
+    a.min(b) - h * h * smoothness / 4.0
+}
+
+fn length([x, y, z]: [f64; 3]) -> f64 {
+    (x * x + y * y + z * z).sqrt()
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn subtract(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+/// Distance from a point to a box of the given half size, centred on the origin
+fn cuboid_distance(point: [f64; 3], half_size: [f64; 3]) -> f64 {
+    let q = [0, 1, 2].map(|i| point[i].abs() - half_size[i]);
+    let outside = length(q.map(|c| c.max(0.0)));
+    let inside = q[0].max(q[1]).max(q[2]).min(0.0);
+    outside + inside
+}
+
+fn mandelbulb_distance(point: [f64; 3], power: f64, iterations: usize) -> f64 {
+    let mut z = point;
+    let mut derivative = 1.0;
+    let mut radius = length(z);
+    for _ in 0..iterations {
+        if radius > 2.0 {
+            break;
+        }
+        let theta = (z[1] / radius.max(f64::MIN_POSITIVE)).acos() * power;
+        let phi = z[2].atan2(z[0]) * power;
+        derivative = power * radius.powf(power - 1.0) * derivative + 1.0;
+        let scaled = radius.powf(power);
+        z = [
+            scaled * theta.sin() * phi.cos() + point[0],
+            scaled * theta.cos() + point[1],
+            scaled * theta.sin() * phi.sin() + point[2],
+        ];
+        radius = length(z);
+    }
+    0.5 * radius.max(f64::MIN_POSITIVE).ln() * radius / derivative
+}
+
+impl DistanceField {
+    /// A field placed by a transformation, as objects are
+    pub fn transformed(
+        field: DistanceField,
+        transformation: AffineTransformation,
+    ) -> DistanceField {
+        DistanceField::Transformed {
+            field: Box::new(field),
+            matrix: AffineMatrix::new(transformation),
+        }
+    }
+
+    /// Distance from the point to the surface, or a lower bound on it, negative inside
+    pub fn distance(&self, point: [f64; 3]) -> f64 {
+        match self {
+            DistanceField::Sphere { radius } => length(point) - radius,
+            DistanceField::Cuboid { half_size } => cuboid_distance(point, *half_size),
+            DistanceField::RoundBox { half_size, radius } => {
+                cuboid_distance(point, half_size.map(|c| c - radius)) - radius
+            }
+            DistanceField::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let [x, y, z] = point;
+                (x.hypot(z) - major_radius).hypot(y) - minor_radius
+            }
+            DistanceField::Capsule { start, end, radius } => {
+                let along = subtract(point, *start);
+                let axis = subtract(*end, *start);
+                let t = (dot(along, axis) / dot(axis, axis)).clamp(0.0, 1.0);
+                length(subtract(along, axis.map(|c| c * t))) - radius
+            }
+            DistanceField::SmoothUnion { fields, smoothness } => {
+                let [a, b] = &**fields;
+                smooth_min(a.distance(point), b.distance(point), *smoothness)
+            }
+            DistanceField::SmoothSubtraction { fields, smoothness } => {
+                let [a, b] = &**fields;
+                -smooth_min(-a.distance(point), b.distance(point), *smoothness)
+            }
+            DistanceField::Repetition { field, period } => {
+                let cell = [0, 1, 2].map(|i| {
+                    if period[i] > 0.0 {
+                        point[i] - period[i] * (point[i] / period[i]).round()
+                    } else {
+                        point[i]
+                    }
+                });
+                field.distance(cell)
+            }
+            DistanceField::Mandelbulb { power, iterations } => {
+                mandelbulb_distance(point, *power, *iterations)
+            }
+            DistanceField::Transformed { field, matrix } => {
+                let shifted = matrix.shift_point(&Point::new(point)).to_array();
+                field.distance(shifted) / matrix.shift_stretch()
+            }
+        }
+    }
+
+    /// Direction in which the distance grows fastest, estimated from samples at the corners
+    /// of a small tetrahedron
+    pub fn normal(&self, point: [f64; 3]) -> Normal {
+        const CORNERS: [[f64; 3]; 4] = [
+            [1.0, -1.0, -1.0],
+            [-1.0, -1.0, 1.0],
+            [-1.0, 1.0, -1.0],
+            [1.0, 1.0, 1.0],
+        ];
+        let mut gradient = [0.0; 3];
+        for corner in CORNERS.iter() {
+            let sample = [0, 1, 2].map(|i| point[i] + corner[i] * GRADIENT_STEP);
+            let distance = self.distance(sample);
+            for i in 0..3 {
+                gradient[i] += corner[i] * distance;
+            }
+        }
+        Normal::new(gradient).normalized()
+    }
+
+    /// Bounds of the surface, or None if it is unbounded
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        let centred = |[x, y, z]: [f64; 3]| Some(BoundingBox::new([-x, -y, -z], [x, y, z]));
+        match self {
+            DistanceField::Sphere { radius } => centred([*radius; 3]),
+            DistanceField::Cuboid { half_size } | DistanceField::RoundBox { half_size, .. } => {
+                centred(*half_size)
+            }
+            DistanceField::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let outer = major_radius + minor_radius;
+                centred([outer, *minor_radius, outer])
+            }
+            DistanceField::Capsule { start, end, radius } => Some(BoundingBox::new(
+                [0, 1, 2].map(|i| start[i].min(end[i]) - radius),
+                [0, 1, 2].map(|i| start[i].max(end[i]) + radius),
+            )),
+            DistanceField::SmoothUnion { fields, smoothness } => {
+                let [a, b] = &**fields;
+                // blending only ever adds a quarter of the smoothness
+                let bounds = a.bounds()?.union(&b.bounds()?);
+                let margin = smoothness / 4.0;
+                Some(BoundingBox::new(
+                    bounds.min.map(|c| c - margin),
+                    bounds.max.map(|c| c + margin),
+                ))
+            }
+            DistanceField::SmoothSubtraction { fields, .. } => fields[0].bounds(),
+            DistanceField::Repetition { field, period } => {
+                if period.iter().any(|&period| period > 0.0) {
+                    None
+                } else {
+                    field.bounds()
+                }
+            }
+            DistanceField::Mandelbulb { .. } => centred([1.25; 3]),
+            DistanceField::Transformed { field, matrix } => {
+                field.bounds().map(|bounds| bounds.transformed(matrix))
+            }
+        }
+    }
+
+    /// The first point in front of the ray on the surface, found by sphere tracing. Rays
+    /// starting on the surface first have to leave it, so that they do not hit it again at
+    /// once. The texture coordinates map the surface like a sphere around the origin.
+    pub fn crossing(&self, ray: &Ray) -> Option<Crossing> {
+        let (near, far) = match self.bounds() {
+            Some(bounds) => bounds.ray_range(ray)?,
+            None => (0.0, MAX_DISTANCE),
+        };
+        let from = ray.from.to_array();
+        let direction = ray.direction.to_array();
+        let point_at = |distance: f64| [0, 1, 2].map(|i| from[i] + distance * direction[i]);
+
+        let mut distance = near;
+        // only the start of the ray can be on the surface already
+        let mut left_surface = near > 0.0;
+        for _ in 0..MAX_STEPS {
+            if distance > far {
+                return None;
+            }
+            let point = point_at(distance);
+            // the magnitude, so that rays inside march out to the surface too
+            let step = self.distance(point).abs();
+            if step < HIT_DISTANCE {
+                if left_surface {
+                    return Some(self.surface_crossing(point, distance));
+                }
+                distance += HIT_DISTANCE;
+            } else {
+                left_surface = true;
+                distance += step;
+            }
+        }
+        None
+    }
+
+    /// Every point along the whole line of the ray where it passes through the surface, behind
+    /// its start as well as in front, in order, found by sphere tracing. Places where the line
+    /// only grazes the surface, coming away on the side it approached from, are left out, so
+    /// that the crossings alternate between entering and leaving.
+    pub fn crossings(&self, ray: &Ray) -> Vec<Crossing> {
+        let bounds = self.bounds();
+        let (start, end) = match &bounds {
+            Some(bounds) => {
+                let backwards = Ray {
+                    from: ray.from.clone(),
+                    direction: ray.direction.reverse(),
+                };
+                match (bounds.ray_range(&backwards), bounds.ray_range(ray)) {
+                    (Some((_, behind)), Some((_, ahead))) => (-behind, ahead),
+                    (Some((near, behind)), None) => (-behind, -near),
+                    (None, Some((near, ahead))) => (near, ahead),
+                    (None, None) => return vec![],
+                }
+            }
+            None => (-MAX_DISTANCE, MAX_DISTANCE),
+        };
+        let from = ray.from.to_array();
+        let direction = ray.direction.to_array();
+        let point_at = |distance: f64| [0, 1, 2].map(|i| from[i] + distance * direction[i]);
+
+        let mut crossings = vec![];
+        let mut distance = start;
+        // the line starts outside the bounds, if there are any
+        let mut inside = bounds.is_none() && self.distance(point_at(start)) < 0.0;
+        // where the line reached the surface it is now stepping across
+        let mut surface = None;
+        for _ in 0..MAX_STEPS {
+            if distance > end {
+                break;
+            }
+            let point = point_at(distance);
+            let signed = self.distance(point);
+            if signed.abs() < HIT_DISTANCE {
+                surface.get_or_insert(distance);
+                distance += HIT_DISTANCE;
+                continue;
+            }
+            if let Some(surface) = surface.take() {
+                if (signed < 0.0) != inside {
+                    crossings.push(self.surface_crossing(point_at(surface), surface));
+                    inside = !inside;
+                }
+            }
+            distance += signed.abs();
+        }
+        // the line leaves the bounds outside the surface
+        if let (Some(surface), true) = (surface, inside) {
+            crossings.push(self.surface_crossing(point_at(surface), surface));
+        }
+        crossings
+    }
+
+    /// The crossing at a point on the surface, with texture coordinates mapping the surface
+    /// like a sphere around the origin
+    fn surface_crossing(&self, point: [f64; 3], distance: f64) -> Crossing {
+        let [x, y, z] = point;
+        let latitude = (y / length(point)).clamp(-1.0, 1.0).asin();
+        Crossing {
+            distance,
+            normal: self.normal(point),
+            texture_coordinates: [0.5 + x.atan2(z) / (2.0 * PI), 0.5 + latitude / PI],
+        }
+    }
+}
+
+#[test]
+fn test_distance_field() {
+    use crate::scene::object::matrix::rotation::Orientation;
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    let sphere = DistanceField::Sphere { radius: 1.0 };
+    assert!(close(sphere.distance([3.0, 0.0, 0.0]), 2.0));
+    assert!(close(sphere.distance([0.0; 3]), -1.0));
+    let cuboid = DistanceField::Cuboid {
+        half_size: [1.0, 2.0, 3.0],
+    };
+    assert!(close(cuboid.distance([4.0, 6.0, 0.0]), 5.0));
+    assert!(close(cuboid.distance([0.5, 0.0, 0.0]), -0.5));
+    let round_box = DistanceField::RoundBox {
+        half_size: [1.0; 3],
+        radius: 0.5,
+    };
+    assert!(close(round_box.distance([2.0, 0.0, 0.0]), 1.0));
+    // the corner is rounded off
+    let corner = 0.5 + 0.5 / 3f64.sqrt();
+    assert!(close(round_box.distance([1.0; 3].map(|c| c * corner)), 0.0));
+    let torus = DistanceField::Torus {
+        major_radius: 2.0,
+        minor_radius: 0.5,
+    };
+    assert!(close(torus.distance([0.0, 0.0, 2.0]), -0.5));
+    assert!(close(torus.distance([0.0; 3]), 1.5));
+    let capsule = DistanceField::Capsule {
+        start: [0.0, -1.0, 0.0],
+        end: [0.0, 1.0, 0.0],
+        radius: 0.5,
+    };
+    assert!(close(capsule.distance([2.0, 0.3, 0.0]), 1.5));
+    assert!(close(capsule.distance([0.0, 3.0, 0.0]), 1.5));
+
+    let two_spheres = |smoothness: f64| DistanceField::SmoothUnion {
+        fields: Box::new([
+            DistanceField::transformed(
+                DistanceField::Sphere { radius: 1.0 },
+                AffineTransformation {
+                    scale: [1.0; 3],
+                    position: [-1.0, 0.0, 0.0],
+                    orientation: Orientation::IDENTITY,
+                },
+            ),
+            DistanceField::transformed(
+                DistanceField::Sphere { radius: 1.0 },
+                AffineTransformation {
+                    scale: [1.0; 3],
+                    position: [1.0, 0.0, 0.0],
+                    orientation: Orientation::IDENTITY,
+                },
+            ),
+        ]),
+        smoothness,
+    };
+    // moving a field keeps its distances, and scaling it scales them
+    let moved = DistanceField::transformed(
+        DistanceField::Sphere { radius: 1.0 },
+        AffineTransformation {
+            scale: [1.0; 3],
+            position: [2.0, 0.0, 0.0],
+            orientation: Orientation::IDENTITY,
+        },
+    );
+    assert!(close(
+        moved.distance([5.0, 0.0, 0.0]),
+        sphere.distance([3.0, 0.0, 0.0])
+    ));
+    assert!(close(moved.distance([2.0, 0.0, 0.0]), -1.0));
+    let grown = DistanceField::transformed(
+        DistanceField::Sphere { radius: 1.0 },
+        AffineTransformation {
+            scale: [2.0; 3],
+            position: [0.0; 3],
+            orientation: Orientation::IDENTITY,
+        },
+    );
+    assert!(close(grown.distance([5.0, 0.0, 0.0]), 3.0));
+    assert!(close(grown.distance([0.0; 3]), -2.0));
+
+    // a bridge forms where the spheres touch
+    let between = [0.0, 0.5, 0.0];
+    assert!(two_spheres(0.0).distance(between) > 0.0);
+    assert!(two_spheres(1.0).distance(between) < 0.0);
+    let carved = DistanceField::SmoothSubtraction {
+        fields: Box::new([cuboid, DistanceField::Sphere { radius: 1.0 }]),
+        smoothness: 0.0,
+    };
+    assert!(close(carved.distance([0.0; 3]), 1.0));
+    let repeated = DistanceField::Repetition {
+        field: Box::new(DistanceField::Sphere { radius: 1.0 }),
+        period: [4.0, 0.0, 0.0],
+    };
+    assert!(close(repeated.distance([8.0, 0.0, 0.0]), -1.0));
+    assert!(close(repeated.distance([6.0, 0.0, 0.0]), 1.0));
+    assert!(repeated.bounds().is_none());
+
+    // traced from outside and from within
+    let ray = |from: [f64; 3], direction: [f64; 3]| Ray {
+        from: Point::new(from),
+        direction: crate::vector::Direction::new(direction).normalized(),
+    };
+    let crossing = two_spheres(0.0)
+        .crossing(&ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]))
+        .unwrap();
+    assert!((crossing.distance - 3.0).abs() < 1e-4);
+    assert!((crossing.normal.to_array()[0] + 1.0).abs() < 1e-4);
+    let crossing = sphere.crossing(&ray([0.0; 3], [0.0, 1.0, 0.0])).unwrap();
+    assert!((crossing.distance - 1.0).abs() < 1e-4);
+    assert!((crossing.normal.to_array()[1] - 1.0).abs() < 1e-4);
+    // leaving the surface, rather than hitting it where the ray starts
+    assert!(sphere
+        .crossing(&ray([0.0, 1.0, 0.0], [0.0, 1.0, 0.0]))
+        .is_none());
+    assert!(sphere
+        .crossing(&ray([0.0, 2.0, 0.0], [1.0, 0.0, 0.0]))
+        .is_none());
+
+    // along the whole line, through both spheres but not out where they touch
+    let crossings = two_spheres(0.0).crossings(&ray([0.5, 0.0, 0.0], [1.0, 0.0, 0.0]));
+    let distances: Vec<f64> = crossings.iter().map(|crossing| crossing.distance).collect();
+    assert_eq!(distances.len(), 2);
+    assert!((distances[0] + 2.5).abs() < 1e-4 && (distances[1] - 1.5).abs() < 1e-4);
+    assert!((crossings[0].normal.to_array()[0] + 1.0).abs() < 1e-4);
+    assert!(sphere
+        .crossings(&ray([0.0, 2.0, 0.0], [1.0, 0.0, 0.0]))
+        .is_empty());
+
+    let bulb = DistanceField::Mandelbulb {
+        power: 8.0,
+        iterations: 8,
+    };
+    assert!(bulb.distance([0.1; 3]) < 0.0);
+    assert!(bulb.distance([0.0; 3]) <= 0.0);
+    assert!(bulb.distance([2.0, 0.0, 0.0]) > 0.5);
+    let crossing = bulb
+        .crossing(&ray([0.0, 0.0, 3.0], [0.0, 0.0, -1.0]))
+        .unwrap();
+    assert!(crossing.distance > 1.5 && crossing.distance < 3.0);
+}
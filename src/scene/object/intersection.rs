@@ -12,19 +12,67 @@ where
 {
     let mut best = None;
     for object in objects {
-        best = object.intersect(ray).and_then(|hit| {
-            let distance = (hit.normal.from.clone() - ray.from.clone()).magnitude_squared();
-            match best {
-                None => Some((distance, object, hit)), // first hit, update best
-                Some((d, o, h)) => {
-                    if d > distance {
-                        Some((distance, object, hit)) // closer hit, update best
-                    } else {
-                        Some((d, o, h)) // further hit, preserve previous best
-                    }
+        best = match (best, object.intersect(ray)) {
+            (best, None) => best, // no hit, preserve previous best
+            (None, Some(hit)) => {
+                let distance = (hit.point.clone() - ray.from.clone()).magnitude_squared();
+                Some((distance, object, hit)) // first hit, update best
+            }
+            (Some((d, o, h)), Some(hit)) => {
+                let distance = (hit.point.clone() - ray.from.clone()).magnitude_squared();
+                if d > distance {
+                    Some((distance, object, hit)) // closer hit, update best
+                } else {
+                    Some((d, o, h)) // further hit, preserve previous best
                 }
             }
-        });
+        };
     }
-    best.and_then(|(_, _, hit)| Some(hit))
+    best.map(|(_, _, hit)| hit)
+}
+
+/// Whether any shadow-casting object is hit closer than `max_distance` along the ray
+pub fn find_any_intersection<'a, O>(
+    objects: impl IntoIterator<Item = &'a O>,
+    ray: &Ray,
+    max_distance: f64,
+) -> bool
+where
+    O: 'a + Intersectable,
+{
+    let max_distance_squared = max_distance * max_distance;
+    objects
+        .into_iter()
+        .filter(|object| object.casts_shadows())
+        .filter_map(|object| object.intersect(ray))
+        .any(|hit| (hit.point - ray.from.clone()).magnitude_squared() < max_distance_squared)
+}
+
+#[test]
+fn test_find_closest_intersection() {
+    use crate::{scene::test_sphere, vector::Vector3};
+    let ray = Ray::new(Vector3::new([0.0; 3]), Vector3::new([0.0, 0.0, -1.0]));
+    let sphere = |position| test_sphere(position, Default::default());
+    // a later miss or a further hit keeps the closest hit so far
+    let objects = [
+        sphere([0.0, 0.0, -10.0]),
+        sphere([0.0, 0.0, -5.0]),
+        sphere([0.0, 0.0, -20.0]),
+        sphere([5.0, 0.0, -5.0]),
+    ];
+    let hit = find_closest_intersection(&objects, &ray).unwrap();
+    assert_eq!(hit.point.to_array(), [0.0, 0.0, -4.0]);
+    assert!(find_closest_intersection(&objects[3..], &ray).is_none());
+}
+
+#[test]
+fn test_find_any_intersection() {
+    use crate::{scene::test_sphere, vector::Vector3};
+    let ray = Ray::new(Vector3::new([0.0; 3]), Vector3::new([0.0, 0.0, -1.0]));
+    let mut objects = [test_sphere([0.0, 0.0, -5.0], Default::default())];
+    assert!(find_any_intersection(&objects, &ray, 10.0));
+    // only hits closer than the limit count
+    assert!(!find_any_intersection(&objects, &ray, 3.0));
+    objects[0].casts_shadows = false;
+    assert!(!find_any_intersection(&objects, &ray, 10.0));
 }
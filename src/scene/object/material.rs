@@ -1,14 +1,50 @@
-use crate::image::Colour;
+use crate::{
+    image::Colour,
+    ray::Hit,
+    vector::{Direction, Normal},
+};
 use std::default::Default;
 
+pub mod texture;
+use texture::Texture;
+
 const EPSILON: f64 = 0.00000000001;
 
+/// How the specular highlight is computed from the light and view directions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpecularModel {
+    /// Angle between the view direction and the reflected light direction
+    Phong,
+    /// Angle between the normal and the half-vector of the light and view directions
+    BlinnPhong,
+}
+
+impl SpecularModel {
+    /// Cosine of the angle the model measures, given unit directions towards the light and
+    /// towards the viewer, before it is raised to the shininess
+    pub fn factor(&self, light: &Direction, normal: &Normal, view: &Direction) -> f64 {
+        match self {
+            SpecularModel::Phong => view.dot(&light.reverse().reflect(normal)),
+            SpecularModel::BlinnPhong => normal.dot(&(light.clone() + view.clone()).normalized()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Material {
     pub ambient: f64,
     pub diffuse: f64,
     pub specular: f64,
+    /// Exponent of the specular term: higher values give smaller, sharper highlights
     pub shininess: f64,
-    pub colour: Colour,
+    pub specular_model: SpecularModel,
+    pub colour: Texture,
+    /// Fraction of the returned colour taken from the mirror reflection, in [0, 1]
+    pub reflectivity: f64,
+    /// Fraction of the returned colour taken from light passing through the surface, in [0, 1]
+    pub transparency: f64,
+    /// Index of refraction of the material, relative to the surrounding air
+    pub refractive_index: f64,
 }
 
 impl Material {
@@ -17,7 +53,11 @@ impl Material {
         diffuse: 1.0 / 3.0,
         specular: 1.0 / 3.0,
         shininess: 4.0,
-        colour: Colour::WHITE,
+        specular_model: SpecularModel::Phong,
+        colour: Texture::Constant(Colour::WHITE),
+        reflectivity: 0.0,
+        transparency: 0.0,
+        refractive_index: 1.0,
     };
 
     pub fn new(
@@ -34,9 +74,19 @@ impl Material {
             diffuse,
             specular,
             shininess,
-            colour,
+            specular_model: SpecularModel::Phong,
+            colour: Texture::Constant(colour),
+            reflectivity: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
         }
     }
+
+    /// Colour of the surface where it was hit
+    pub fn get_colour(&self, hit: &Hit) -> Colour {
+        self.colour
+            .get_colour(hit.texture_coordinates, &hit.object_point)
+    }
 }
 
 impl Default for Material {
@@ -44,3 +94,21 @@ impl Default for Material {
         Material::DEFAULT
     }
 }
+
+#[test]
+fn test_specular_factor() {
+    let normal = Normal::new([0.0, 1.0, 0.0]);
+    let light = Direction::new([1.0, 1.0, 0.0]).normalized();
+    // viewer in the mirror direction: both models peak
+    let mirror = Direction::new([-1.0, 1.0, 0.0]).normalized();
+    for model in [SpecularModel::Phong, SpecularModel::BlinnPhong].iter() {
+        assert!((model.factor(&light, &normal, &mirror) - 1.0).abs() < 1e-12);
+    }
+    // viewer along the normal: Phong measures the 45° to the reflection,
+    // Blinn-Phong the 22.5° between the half vector and the normal
+    let above = Direction::new([0.0, 1.0, 0.0]);
+    let phong = SpecularModel::Phong.factor(&light, &normal, &above);
+    assert!((phong - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+    let blinn = SpecularModel::BlinnPhong.factor(&light, &normal, &above);
+    assert!((blinn - std::f64::consts::FRAC_PI_8.cos()).abs() < 1e-12);
+}
@@ -0,0 +1,375 @@
+use crate::{
+    image::{Colour, Image},
+    vector::Point,
+};
+use std::{f64::consts::PI, sync::Arc};
+
+pub mod noise;
+
+/// How texture coordinates outside [0, 1] are brought back onto the image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Addressing {
+    /// Repeat the image
+    Wrap,
+    /// Extend the edge pixels
+    Clamp,
+    /// Repeat the image, flipping every other copy so that the edges meet
+    Mirror,
+}
+
+impl Addressing {
+    /// Bring a pixel index back into [0, size)
+    fn apply(&self, index: isize, size: usize) -> usize {
+        let size = size as isize;
+        let index = match self {
+            Addressing::Wrap => index.rem_euclid(size),
+            Addressing::Clamp => index.clamp(0, size - 1),
+            Addressing::Mirror => {
+                let index = index.rem_euclid(2 * size);
+                if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }
+            }
+        };
+        index as usize
+    }
+}
+
+/// How colours between pixel centres are found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filtering {
+    /// The pixel the point falls in
+    Nearest,
+    /// Linear interpolation between the four closest pixel centres
+    Bilinear,
+}
+
+/// An image mapped onto a surface by texture coordinates, where u goes across the image from
+/// its left edge and v goes up from its bottom edge
+#[derive(Clone, Debug)]
+pub struct ImageTexture {
+    /// Shared, so that cloning materials does not copy the image
+    image: Arc<Image>,
+    pub filtering: Filtering,
+    pub addressing: Addressing,
+}
+
+impl ImageTexture {
+    /// A bilinearly filtered, wrapped texture. The image should hold linear light, so images
+    /// read from sRGB files need decoding first.
+    pub fn new(image: Image) -> ImageTexture {
+        ImageTexture {
+            image: Arc::new(image),
+            filtering: Filtering::Bilinear,
+            addressing: Addressing::Wrap,
+        }
+    }
+
+    pub fn sample(&self, [u, v]: [f64; 2]) -> Colour {
+        let (height, width) = self.image.pixels.dim();
+        if height == 0 || width == 0 {
+            return Colour::BLACK;
+        }
+        // in pixels from the top left corner of the image
+        let x = u * width as f64;
+        let y = (1.0 - v) * height as f64;
+        let pixel = |row: f64, column: f64| {
+            let row = self.addressing.apply(row as isize, height);
+            let column = self.addressing.apply(column as isize, width);
+            self.image.pixels[(row, column)]
+        };
+        match self.filtering {
+            Filtering::Nearest => pixel(y.floor(), x.floor()),
+            Filtering::Bilinear => {
+                // relative to the centre of the pixel above and left of the point
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (column, row) = (x.floor(), y.floor());
+                let (dx, dy) = (x - column, y - row);
+                let top = pixel(row, column).scale(1.0 - dx) + pixel(row, column + 1.0).scale(dx);
+                let bottom = pixel(row + 1.0, column).scale(1.0 - dx)
+                    + pixel(row + 1.0, column + 1.0).scale(dx);
+                top.scale(1.0 - dy) + bottom.scale(dy)
+            }
+        }
+    }
+}
+
+/// Where on a surface a procedural texture is evaluated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureSpace {
+    /// The hit's texture coordinates, as the point (u, v, 0)
+    TextureCoordinates,
+    /// The hit point in the space of the shape, so that the pattern moves with the object
+    Object,
+}
+
+/// Which gradient noise function a texture uses
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Noise {
+    Perlin,
+    Simplex,
+}
+
+impl Noise {
+    fn function(&self) -> fn([f64; 3]) -> f64 {
+        match self {
+            Noise::Perlin => noise::perlin,
+            Noise::Simplex => noise::simplex,
+        }
+    }
+}
+
+/// The colour of a surface, which may vary across it. Procedural textures blend between two
+/// colours, and `scale` is the size of their features, in the units of their texture space.
+#[derive(Clone, Debug)]
+pub enum Texture {
+    Constant(Colour),
+    Image(ImageTexture),
+    /// Another texture multiplied by a colour
+    Tinted {
+        tint: Colour,
+        texture: Box<Texture>,
+    },
+    /// Alternating squares, or cubes in object space, `scale` across
+    Checkerboard {
+        colours: [Colour; 2],
+        scale: f64,
+        space: TextureSpace,
+    },
+    /// From the first colour where the dot product of the point and `direction` is 0, to the
+    /// second where it is 1, so the blend is one over the length of `direction` long
+    Gradient {
+        colours: [Colour; 2],
+        direction: [f64; 3],
+        space: TextureSpace,
+    },
+    /// Smooth random blotches
+    Noise {
+        colours: [Colour; 2],
+        scale: f64,
+        noise: Noise,
+        space: TextureSpace,
+    },
+    /// Fractal noise, summed over octaves of increasing detail
+    Turbulence {
+        colours: [Colour; 2],
+        scale: f64,
+        octaves: usize,
+        space: TextureSpace,
+    },
+    /// Veins along x, `scale` apart, distorted by turbulence of the given strength
+    Marble {
+        colours: [Colour; 2],
+        scale: f64,
+        octaves: usize,
+        turbulence: f64,
+        space: TextureSpace,
+    },
+    /// Rings around the y axis, `scale` apart, distorted by turbulence of the given strength
+    Wood {
+        colours: [Colour; 2],
+        scale: f64,
+        octaves: usize,
+        turbulence: f64,
+        space: TextureSpace,
+    },
+}
+
+/// Blend from `a`, at t = 0, to `b`, at t = 1
+fn mix([a, b]: [Colour; 2], t: f64) -> Colour {
+    a.scale(1.0 - t) + b.scale(t)
+}
+
+impl Texture {
+    /// Colour at a point, given by its texture coordinates and position in object space
+    pub fn get_colour(&self, texture_coordinates: [f64; 2], object_point: &Point) -> Colour {
+        let point = |space: &TextureSpace, scale: f64| {
+            let point = match space {
+                TextureSpace::TextureCoordinates => {
+                    [texture_coordinates[0], texture_coordinates[1], 0.0]
+                }
+                TextureSpace::Object => object_point.to_array(),
+            };
+            point.map(|c| c / scale)
+        };
+        match self {
+            Texture::Constant(colour) => *colour,
+            Texture::Image(image) => image.sample(texture_coordinates),
+            Texture::Tinted { tint, texture } => {
+                *tint * texture.get_colour(texture_coordinates, object_point)
+            }
+            Texture::Checkerboard {
+                colours,
+                scale,
+                space,
+            } => {
+                let cells: i64 = point(space, *scale).iter().map(|c| c.floor() as i64).sum();
+                colours[cells.rem_euclid(2) as usize]
+            }
+            Texture::Gradient {
+                colours,
+                direction,
+                space,
+            } => {
+                let point = point(space, 1.0);
+                let t: f64 = (0..3).map(|i| point[i] * direction[i]).sum();
+                mix(*colours, t.clamp(0.0, 1.0))
+            }
+            Texture::Noise {
+                colours,
+                scale,
+                noise,
+                space,
+            } => {
+                let value = noise.function()(point(space, *scale));
+                mix(*colours, ((value + 1.0) / 2.0).clamp(0.0, 1.0))
+            }
+            Texture::Turbulence {
+                colours,
+                scale,
+                octaves,
+                space,
+            } => {
+                let value = noise::turbulence(noise::perlin, point(space, *scale), *octaves);
+                mix(*colours, value.clamp(0.0, 1.0))
+            }
+            Texture::Marble {
+                colours,
+                scale,
+                octaves,
+                turbulence,
+                space,
+            } => {
+                let point = point(space, *scale);
+                let distortion = turbulence * noise::turbulence(noise::perlin, point, *octaves);
+                let value = ((point[0] + distortion) * PI).sin();
+                mix(*colours, (value + 1.0) / 2.0)
+            }
+            Texture::Wood {
+                colours,
+                scale,
+                octaves,
+                turbulence,
+                space,
+            } => {
+                let point = point(space, *scale);
+                let distortion = turbulence * noise::turbulence(noise::perlin, point, *octaves);
+                let rings = point[0].hypot(point[2]) + distortion;
+                mix(*colours, rings - rings.floor())
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sample() {
+    use crate::image::Resolution;
+    // black on the left, white on the right
+    let mut image = Image::new(&Resolution {
+        height: 2,
+        width: 2,
+    });
+    image.pixels[(0, 1)] = Colour::WHITE;
+    image.pixels[(1, 1)] = Colour::WHITE;
+    let mut texture = ImageTexture::new(image);
+
+    texture.filtering = Filtering::Nearest;
+    assert_eq!(texture.sample([0.3, 0.9]).red, 0.0);
+    assert_eq!(texture.sample([0.7, 0.1]).red, 1.0);
+    assert_eq!(texture.sample([1.3, 0.5]).red, 0.0);
+    texture.addressing = Addressing::Clamp;
+    assert_eq!(texture.sample([1.3, 0.5]).red, 1.0);
+    texture.addressing = Addressing::Mirror;
+    assert_eq!(texture.sample([1.3, 0.5]).red, 1.0);
+    assert_eq!(texture.sample([1.8, 0.5]).red, 0.0);
+
+    texture.filtering = Filtering::Bilinear;
+    assert_eq!(texture.sample([0.5, 0.5]).green, 0.5);
+    assert_eq!(texture.sample([0.25, 0.5]).green, 0.0);
+    assert_eq!(texture.sample([0.375, 0.5]).green, 0.25);
+    // across the edge, into the mirrored copy
+    assert_eq!(texture.sample([1.0, 0.5]).green, 1.0);
+    texture.addressing = Addressing::Wrap;
+    assert_eq!(texture.sample([1.0, 0.5]).green, 0.5);
+}
+
+#[test]
+fn test_procedural_textures() {
+    let colours = [Colour::BLACK, Colour::WHITE];
+    let origin = Point::new([0.0; 3]);
+    let checkerboard = Texture::Checkerboard {
+        colours,
+        scale: 0.5,
+        space: TextureSpace::TextureCoordinates,
+    };
+    assert_eq!(checkerboard.get_colour([0.25, 0.25], &origin).red, 0.0);
+    assert_eq!(checkerboard.get_colour([0.75, 0.25], &origin).red, 1.0);
+    assert_eq!(checkerboard.get_colour([0.75, 0.75], &origin).red, 0.0);
+    let cubes = Texture::Checkerboard {
+        colours,
+        scale: 1.0,
+        space: TextureSpace::Object,
+    };
+    let red =
+        |texture: &Texture, point: [f64; 3]| texture.get_colour([0.0; 2], &Point::new(point)).red;
+    assert_eq!(red(&cubes, [0.5, 0.5, 0.5]), 0.0);
+    assert_eq!(red(&cubes, [0.5, 0.5, -0.5]), 1.0);
+    assert_eq!(red(&cubes, [-0.5, -0.5, 0.5]), 0.0);
+
+    let gradient = Texture::Gradient {
+        colours,
+        direction: [0.0, 0.5, 0.0],
+        space: TextureSpace::Object,
+    };
+    assert_eq!(red(&gradient, [5.0, 1.0, 0.0]), 0.5);
+    assert_eq!(red(&gradient, [0.0, 3.0, 0.0]), 1.0);
+
+    let tinted = Texture::Tinted {
+        tint: Colour::WHITE.scale(0.5),
+        texture: Box::new(gradient),
+    };
+    assert_eq!(red(&tinted, [0.0, 3.0, 0.0]), 0.5);
+
+    // every other texture stays between its colours
+    let space = TextureSpace::Object;
+    let textures = [
+        Texture::Noise {
+            colours,
+            scale: 0.3,
+            noise: Noise::Simplex,
+            space,
+        },
+        Texture::Turbulence {
+            colours,
+            scale: 0.3,
+            octaves: 4,
+            space,
+        },
+        Texture::Marble {
+            colours,
+            scale: 0.3,
+            octaves: 4,
+            turbulence: 2.0,
+            space,
+        },
+        Texture::Wood {
+            colours,
+            scale: 0.1,
+            octaves: 2,
+            turbulence: 0.5,
+            space,
+        },
+    ];
+    for texture in textures.iter() {
+        let values: Vec<f64> = (0..200)
+            .map(|i| {
+                let i = i as f64 * 0.05;
+                red(texture, [i.sin(), i * 0.3, i.cos()])
+            })
+            .collect();
+        assert!(values.iter().all(|value| (0.0..=1.0).contains(value)));
+        assert!(values.iter().any(|&value| value != values[0]));
+    }
+}
@@ -0,0 +1,165 @@
+//! Gradient noise: smooth pseudo-random functions of space, in roughly [-1, 1], with features
+//! about one unit across
+use crate::random::Random;
+use std::sync::OnceLock;
+
+/// A shuffle of 0 to 255, repeated twice so that sums of two entries can index it
+fn permutation() -> &'static [usize; 512] {
+    static PERMUTATION: OnceLock<[usize; 512]> = OnceLock::new();
+    PERMUTATION.get_or_init(|| {
+        let mut random = Random::new(0);
+        let mut shuffled: Vec<usize> = (0..256).collect();
+        for i in (1..256).rev() {
+            let j = random.next_u64() as usize % (i + 1);
+            shuffled.swap(i, j);
+        }
+        let mut permutation = [0; 512];
+        for (i, entry) in permutation.iter_mut().enumerate() {
+            *entry = shuffled[i % 256];
+        }
+        permutation
+    })
+}
+
+/// Ken Perlin's improved noise
+pub fn perlin([x, y, z]: [f64; 3]) -> f64 {
+    let p = permutation();
+    // unit cube containing the point, and the point's position within it
+    let cell = |c: f64| (c.floor() as i64 & 255) as usize;
+    let (xi, yi, zi) = (cell(x), cell(y), cell(z));
+    let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+    let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+    let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+    // dot product with one of 12 gradients along the edges of a cube
+    let gradient = |hash: usize, x: f64, y: f64, z: f64| {
+        let h = hash & 15;
+        let a = if h < 8 { x } else { y };
+        let b = match h {
+            0..=3 => y,
+            12 | 14 => x,
+            _ => z,
+        };
+        (if h & 1 == 0 { a } else { -a }) + (if h & 2 == 0 { b } else { -b })
+    };
+
+    let a = p[xi] + yi;
+    let (aa, ab) = (p[a] + zi, p[a + 1] + zi);
+    let b = p[xi + 1] + yi;
+    let (ba, bb) = (p[b] + zi, p[b + 1] + zi);
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, gradient(p[aa], x, y, z), gradient(p[ba], x - 1.0, y, z)),
+            lerp(
+                u,
+                gradient(p[ab], x, y - 1.0, z),
+                gradient(p[bb], x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                gradient(p[aa + 1], x, y, z - 1.0),
+                gradient(p[ba + 1], x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                gradient(p[ab + 1], x, y - 1.0, z - 1.0),
+                gradient(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+
+/// Simplex noise, after Stefan Gustavson's description, which sums over the 4 corners of the
+/// enclosing tetrahedron rather than the 8 of a cube
+pub fn simplex(point: [f64; 3]) -> f64 {
+    const GRADIENTS: [[f64; 3]; 12] = [
+        [1.0, 1.0, 0.0],
+        [-1.0, 1.0, 0.0],
+        [1.0, -1.0, 0.0],
+        [-1.0, -1.0, 0.0],
+        [1.0, 0.0, 1.0],
+        [-1.0, 0.0, 1.0],
+        [1.0, 0.0, -1.0],
+        [-1.0, 0.0, -1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, -1.0, 1.0],
+        [0.0, 1.0, -1.0],
+        [0.0, -1.0, -1.0],
+    ];
+    const SKEW: f64 = 1.0 / 3.0;
+    const UNSKEW: f64 = 1.0 / 6.0;
+    let p = permutation();
+
+    // skew space so that the tetrahedra become cubes, to find the enclosing cell
+    let skew = point.iter().sum::<f64>() * SKEW;
+    let cell = point.map(|c| (c + skew).floor());
+    let unskew = cell.iter().sum::<f64>() * UNSKEW;
+    let first = [0, 1, 2].map(|i| point[i] - (cell[i] - unskew));
+
+    // the order of the offsets decides which tetrahedron of the cube the point is in
+    let mut axes = [0, 1, 2];
+    axes.sort_by(|&a, &b| first[b].total_cmp(&first[a]));
+    let mut second_offset = [0.0; 3];
+    second_offset[axes[0]] = 1.0;
+    let mut third_offset = second_offset;
+    third_offset[axes[1]] = 1.0;
+    let corners = [[0.0; 3], second_offset, third_offset, [1.0; 3]];
+
+    let wrapped = cell.map(|c| (c as i64 & 255) as usize);
+    corners
+        .iter()
+        .enumerate()
+        .map(|(n, offset)| {
+            let position = [0, 1, 2].map(|i| first[i] - offset[i] + n as f64 * UNSKEW);
+            let falloff = 0.6 - position.iter().map(|c| c * c).sum::<f64>();
+            if falloff <= 0.0 {
+                return 0.0;
+            }
+            let index = |i: usize| wrapped[i] + offset[i] as usize;
+            let hash = p[index(0) + p[index(1) + p[index(2)]]] % 12;
+            let gradient = GRADIENTS[hash];
+            let dot: f64 = (0..3).map(|i| gradient[i] * position[i]).sum();
+            falloff.powi(4) * dot
+        })
+        .sum::<f64>()
+        * 32.0
+}
+
+/// Sum of the magnitudes of `octaves` layers of noise, each at twice the frequency and half
+/// the amplitude of the last, giving a fractal pattern in roughly [0, 1]
+pub fn turbulence(noise: fn([f64; 3]) -> f64, point: [f64; 3], octaves: usize) -> f64 {
+    (0..octaves)
+        .map(|octave| {
+            let frequency = (1 << octave) as f64;
+            noise(point.map(|c| c * frequency)).abs() / frequency
+        })
+        .sum()
+}
+
+#[test]
+fn test_noise() {
+    for noise in [perlin, simplex] {
+        let values: Vec<f64> = (0..1000)
+            .map(|i| {
+                let i = i as f64;
+                noise([i * 0.37, i * 0.11 - 3.0, i * 0.07 + 1.5])
+            })
+            .collect();
+        assert!(values.iter().all(|value| value.abs() <= 1.0));
+        // not constant, and centred on 0
+        assert!(values.iter().any(|value| value.abs() > 0.2));
+        assert!((values.iter().sum::<f64>() / 1000.0).abs() < 0.1);
+        // continuous
+        let point = [1.3, 2.7, -0.4];
+        let nearby = [1.3 + 1e-7, 2.7, -0.4];
+        assert!((noise(point) - noise(nearby)).abs() < 1e-5);
+    }
+    // Perlin noise is 0 at the lattice points
+    assert_eq!(perlin([3.0, -2.0, 7.0]), 0.0);
+    assert!(turbulence(perlin, [0.3, 0.6, 0.9], 4) >= 0.0);
+}
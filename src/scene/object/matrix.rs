@@ -1,22 +1,23 @@
 use ndarray::{array, s, Array2};
 use std::default::Default;
 
-use crate::ray::Ray;
-use crate::vector::HVector;
+use crate::ray::{Hit, Ray};
+use crate::vector::{Direction, HVector, Normal, Point};
 
-type Angle = f64; //TODO
+pub mod rotation;
+use rotation::Orientation;
 
 pub struct AffineTransformation {
     pub scale: [f64; 3],
     pub position: [f64; 3],
-    pub orientation: (Angle, Angle),
+    pub orientation: Orientation,
 }
 
 impl AffineTransformation {
     const DEFAULT: AffineTransformation = AffineTransformation {
         scale: [1.0, 1.0, 1.0],
-        position: [0.0, 0.0, 5.0],
-        orientation: (0.0, 0.0),
+        position: [0.0, 0.0, -5.0],
+        orientation: Orientation::IDENTITY,
     };
 }
 
@@ -26,15 +27,86 @@ impl Default for AffineTransformation {
     }
 }
 
+/// The affine map from an object's space to the world, which objects are placed by, and its
+/// inverse
+#[derive(Clone, Debug)]
 pub struct AffineMatrix {
     actual: Array2<f64>,
     inverse: Array2<f64>,
 }
 
+/// Embed a 3x3 matrix in the top left of a 4x4 one
+fn linear(matrix: [[f64; 3]; 3]) -> Array2<f64> {
+    let mut result = Array2::eye(4);
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, element) in row.iter().enumerate() {
+            result[(i, j)] = *element;
+        }
+    }
+    result
+}
+
+/// The inverse of a square matrix by Gauss-Jordan elimination, or None if it is singular
+/// Largest eigenvalue of a symmetric 3x3 matrix, from the trigonometric solution of its
+/// characteristic cubic, whose roots are all real
+fn largest_eigenvalue(matrix: Array2<f64>) -> f64 {
+    let off_diagonal = matrix[[0, 1]].powi(2) + matrix[[0, 2]].powi(2) + matrix[[1, 2]].powi(2);
+    let mean = matrix.diag().sum() / 3.0;
+    let spread =
+        ((0..3).map(|i| (matrix[[i, i]] - mean).powi(2)).sum::<f64>() + 2.0 * off_diagonal) / 6.0;
+    if spread <= 0.0 {
+        return mean; // a multiple of the identity
+    }
+    let spread = spread.sqrt();
+    // eigenvalues of (matrix - mean I) / spread are 2 cos(angle + 2 k pi / 3)
+    let b = (matrix - Array2::<f64>::eye(3) * mean) / spread;
+    let determinant = b[[0, 0]] * (b[[1, 1]] * b[[2, 2]] - b[[1, 2]] * b[[2, 1]])
+        - b[[0, 1]] * (b[[1, 0]] * b[[2, 2]] - b[[1, 2]] * b[[2, 0]])
+        + b[[0, 2]] * (b[[1, 0]] * b[[2, 1]] - b[[1, 1]] * b[[2, 0]]);
+    let angle = (determinant / 2.0).clamp(-1.0, 1.0).acos() / 3.0;
+    mean + 2.0 * spread * angle.cos()
+}
+
+fn invert(matrix: &Array2<f64>) -> Option<Array2<f64>> {
+    let size = matrix.nrows();
+    let largest = matrix
+        .iter()
+        .fold(0.0, |largest: f64, x| largest.max(x.abs()));
+    let mut left = matrix.clone();
+    let mut right = Array2::eye(size);
+    for column in 0..size {
+        // swap up the row with the largest pivot, for stability
+        let pivot = (column..size)
+            .max_by(|&a, &b| left[(a, column)].abs().total_cmp(&left[(b, column)].abs()))?;
+        if left[(pivot, column)].abs() <= largest * f64::EPSILON * size as f64 {
+            return None;
+        }
+        for matrix in [&mut left, &mut right] {
+            for j in 0..size {
+                matrix.swap((column, j), (pivot, j));
+            }
+        }
+        let scale = 1.0 / left[(column, column)];
+        left.row_mut(column).mapv_inplace(|x| x * scale);
+        right.row_mut(column).mapv_inplace(|x| x * scale);
+        for row in 0..size {
+            let factor = left[(row, column)];
+            if row == column || factor == 0.0 {
+                continue;
+            }
+            let left_pivot_row = left.row(column).to_owned();
+            let right_pivot_row = right.row(column).to_owned();
+            left.row_mut(row).scaled_add(-factor, &left_pivot_row);
+            right.row_mut(row).scaled_add(-factor, &right_pivot_row);
+        }
+    }
+    Some(right)
+}
+
 impl AffineMatrix {
+    /// Scale, then rotate, then move to the position
     pub fn new(transformation: AffineTransformation) -> AffineMatrix {
         let scale = transformation.scale;
-        let (ry, rz) = transformation.orientation;
         let position = transformation.position;
         let scaling = array![
             [scale[0], 0.0, 0.0, 0.0],
@@ -42,23 +114,11 @@ impl AffineMatrix {
             [0.0, 0.0, scale[2], 0.0],
             [0.0, 0.0, 0.0, 1.0],
         ];
-        let rotation_y = array![
-            [ry.cos(), 0.0, ry.sin(), 0.0],
-            [0.0, 1.0, 0.0, 0.0],
-            [-ry.sin(), 0.0, ry.cos(), 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ];
-        let rotation_z = array![
-            [rz.cos(), -rz.sin(), 0.0, 0.0],
-            [rz.sin(), rz.cos(), 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ];
-        let rotation = rotation_z.dot(&rotation_y);
+        let rotation = linear(transformation.orientation.to_quaternion().to_matrix());
         let translation = array![
             [1.0, 0.0, 0.0, position[0]],
             [0.0, 1.0, 0.0, position[1]],
-            [0.0, 0.0, 1.0, -position[2]],
+            [0.0, 0.0, 1.0, position[2]],
             [0.0, 0.0, 0.0, 1.0],
         ];
         let actual = translation.dot(&rotation).dot(&scaling);
@@ -72,63 +132,201 @@ impl AffineMatrix {
         let inverse_translation = array![
             [1.0, 0.0, 0.0, -position[0]],
             [0.0, 1.0, 0.0, -position[1]],
-            [0.0, 0.0, 1.0, position[2]],
-            [0.0, 0.0, 0.0, 1.0],
-        ];
-        let arz = -rz;
-        let inverse_rotation_z = array![
-            [arz.cos(), -arz.sin(), 0.0, 0.0],
-            [arz.sin(), arz.cos(), 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ];
-        let ary = -ry;
-        let inverse_rotation_y = array![
-            [ary.cos(), 0.0, ary.sin(), 0.0],
-            [0.0, 1.0, 0.0, 0.0],
-            [-ary.sin(), 0.0, ary.cos(), 0.0],
+            [0.0, 0.0, 1.0, -position[2]],
             [0.0, 0.0, 0.0, 1.0],
         ];
-        let inverse_rotation = inverse_rotation_y.dot(&inverse_rotation_z);
+        // the inverse of a rotation is its transpose
+        let inverse_rotation = rotation.t().to_owned();
         let inverse = inverse_scaling.dot(&inverse_rotation.dot(&inverse_translation));
         AffineMatrix { actual, inverse }
     }
 
-    pub fn shift_point(&self, point: &HVector) -> HVector {
-        HVector::from_array4(self.inverse.dot(point.get()))
+    pub fn identity() -> AffineMatrix {
+        AffineMatrix {
+            actual: Array2::eye(4),
+            inverse: Array2::eye(4),
+        }
     }
 
-    pub fn shift_vector(&self, vector: &HVector) -> HVector {
-        HVector::from_array3(
+    /// From any invertible matrix, by rows, taking column vectors from object space to the
+    /// world. None if it is singular, or its bottom row is not [0, 0, 0, 1], as it would then
+    /// not be affine.
+    pub fn from_matrix(matrix: [[f64; 4]; 4]) -> Option<AffineMatrix> {
+        if matrix[3] != [0.0, 0.0, 0.0, 1.0] {
+            return None;
+        }
+        let actual = Array2::from_shape_fn((4, 4), |(i, j)| matrix[i][j]);
+        let inverse = invert(&actual)?;
+        Some(AffineMatrix { actual, inverse })
+    }
+
+    /// The transformation which applies this one, then `other`, as for an object within a
+    /// group placed by `other`
+    pub fn then(&self, other: &AffineMatrix) -> AffineMatrix {
+        AffineMatrix {
+            actual: other.actual.dot(&self.actual),
+            inverse: self.inverse.dot(&other.inverse),
+        }
+    }
+
+    /// The most that shifting can lengthen a vector: the largest singular value of the linear
+    /// part of the inverse, which is 1 for rotations and translations
+    pub fn shift_stretch(&self) -> f64 {
+        let linear = self.inverse.slice(s![..3, ..3]);
+        largest_eigenvalue(linear.t().dot(&linear)).sqrt()
+    }
+
+    pub fn shift_point(&self, point: &Point) -> Point {
+        Point::from_hvector(HVector::from_array4(self.inverse.dot(point.get())))
+    }
+
+    pub fn shift_direction(&self, direction: &Direction) -> Direction {
+        Direction::from_hvector(HVector::from_array3(
             self.inverse
                 .slice(s![..3, ..3])
-                .dot(&vector.get().slice(s![..3])),
-        )
+                .dot(&direction.get().slice(s![..3])),
+        ))
     }
 
     pub fn shift(&self, ray: &Ray) -> Ray {
         Ray {
             from: self.shift_point(&ray.from),
-            direction: self.shift_vector(&ray.direction).normalized(),
+            direction: self.shift_direction(&ray.direction).normalized(),
         }
     }
 
-    pub fn unshift_point(&self, point: &HVector) -> HVector {
-        HVector::from_array4(self.actual.dot(point.get()))
+    pub fn unshift_point(&self, point: &Point) -> Point {
+        Point::from_hvector(HVector::from_array4(self.actual.dot(point.get())))
     }
 
-    pub fn unshift_vector(&self, vector: &HVector) -> HVector {
-        HVector::from_array3(
+    pub fn unshift_direction(&self, direction: &Direction) -> Direction {
+        Direction::from_hvector(HVector::from_array3(
             self.actual
                 .slice(s![..3, ..3])
-                .dot(&vector.get().slice(s![..3])),
-        )
+                .dot(&direction.get().slice(s![..3])),
+        ))
+    }
+
+    /// By the transpose of the inverse, which keeps normals perpendicular to surfaces that the
+    /// matrix stretches unevenly, normalized
+    pub fn unshift_normal(&self, normal: &Normal) -> Normal {
+        Normal::from_hvector(HVector::from_array3(
+            self.inverse
+                .slice(s![..3, ..3])
+                .t()
+                .dot(&normal.get().slice(s![..3])),
+        ))
+        .normalized()
     }
 
     pub fn unshift(&self, ray: &Ray) -> Ray {
         Ray {
             from: self.unshift_point(&ray.from),
-            direction: self.unshift_vector(&ray.direction).normalized(),
+            direction: self.unshift_direction(&ray.direction).normalized(),
+        }
+    }
+
+    /// A hit in object space, moved out to the space of the incoming rays apart from its
+    /// object point
+    pub fn unshift_hit<'a>(&self, hit: Hit<'a>) -> Hit<'a> {
+        Hit {
+            point: self.unshift_point(&hit.point),
+            normal: self.unshift_normal(&hit.normal),
+            ..hit
         }
     }
 }
+
+#[test]
+fn test_affine_matrix() {
+    use rotation::EulerOrder;
+    use std::f64::consts::PI;
+    let close = |a: Point, b: [f64; 3]| {
+        let a = a.to_array();
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-12)
+    };
+    // turn about y, then z, as objects were oriented by a pair of angles
+    let matrix = AffineMatrix::new(AffineTransformation {
+        scale: [2.0, 1.0, 1.0],
+        position: [0.0, 0.0, -5.0],
+        orientation: Orientation::Euler {
+            angles: [0.0, PI / 2.0, PI / 2.0],
+            order: EulerOrder::Yzx,
+        },
+    });
+    // x is stretched, turned onto -z, then left there
+    let point = Point::new([1.0, 0.0, 0.0]);
+    assert!(close(matrix.unshift_point(&point), [0.0, 0.0, -7.0]));
+    assert!(close(
+        matrix.shift_point(&matrix.unshift_point(&point)),
+        [1.0, 0.0, 0.0]
+    ));
+
+    // a shear, which no transformation gives
+    let shear = AffineMatrix::from_matrix([
+        [1.0, 0.5, 0.0, 1.0],
+        [0.0, 1.0, 0.0, 2.0],
+        [0.0, 0.0, 3.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ])
+    .unwrap();
+    let point = Point::new([1.0, 2.0, 3.0]);
+    assert!(close(shear.unshift_point(&point), [3.0, 4.0, 9.0]));
+    assert!(close(
+        shear.shift_point(&shear.unshift_point(&point)),
+        [1.0, 2.0, 3.0]
+    ));
+    // normals stay perpendicular to the directions along their surfaces
+    let normal = shear.unshift_normal(&Normal::new([0.0, 1.0, 0.0]));
+    assert!((normal.to_array()[1] - 1.0).abs() < 1e-12);
+    let normal = shear.unshift_normal(&Normal::new([1.0, 1.0, 1.0]));
+    for along in [[1.0, -1.0, 0.0], [0.0, 1.0, -1.0]] {
+        let along = shear.unshift_direction(&Direction::new(along));
+        assert!(normal.dot(&along).abs() < 1e-12);
+    }
+    let singular = [
+        [1.0, 2.0, 0.0, 0.0],
+        [2.0, 4.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    assert!(AffineMatrix::from_matrix(singular).is_none());
+    let mut projective = [[0.0; 4]; 4];
+    for (i, row) in projective.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    projective[3][2] = 1.0;
+    assert!(AffineMatrix::from_matrix(projective).is_none());
+
+    // only scaling and shearing stretch shifted vectors
+    let stretch = |matrix: &AffineMatrix| matrix.shift_stretch();
+    assert!((stretch(&AffineMatrix::identity()) - 1.0).abs() < 1e-12);
+    let turned = AffineMatrix::new(AffineTransformation {
+        scale: [1.0; 3],
+        position: [3.0, -2.0, 1.0],
+        orientation: Orientation::Euler {
+            angles: [0.3, 1.2, -0.7],
+            order: EulerOrder::Yzx,
+        },
+    });
+    assert!((stretch(&turned) - 1.0).abs() < 1e-12);
+    assert!((stretch(&matrix) - 1.0).abs() < 1e-12);
+    let squashed = AffineMatrix::new(AffineTransformation {
+        scale: [2.0, 1.0, 0.5],
+        ..AffineTransformation::default()
+    });
+    assert!((stretch(&squashed) - 2.0).abs() < 1e-12);
+    // the largest singular value of [[1, -0.5], [0, 1]]
+    let expected = ((2.25 + 1.0625f64.sqrt()) / 2.0).sqrt();
+    assert!((stretch(&shear) - expected).abs() < 1e-12);
+
+    // sheared, then turned and moved
+    let composed = shear.then(&matrix);
+    let expected = matrix.unshift_point(&shear.unshift_point(&point));
+    assert!(close(composed.unshift_point(&point), expected.to_array()));
+    assert!(close(composed.shift_point(&expected), [1.0, 2.0, 3.0]));
+    assert!(close(
+        AffineMatrix::identity().then(&shear).unshift_point(&point),
+        [3.0, 4.0, 9.0]
+    ));
+}
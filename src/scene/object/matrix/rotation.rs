@@ -0,0 +1,217 @@
+use std::ops::Mul;
+
+/// In radians, anticlockwise when looking back along the axis of rotation
+pub type Angle = f64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// A rotation, as a quaternion of unit length
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Rotation by `angle` about `axis`, which need not be of unit length but must not be 0
+    pub fn from_axis_angle(axis: [f64; 3], angle: Angle) -> Quaternion {
+        let length = axis.iter().map(|c| c * c).sum::<f64>().sqrt();
+        assert!(length > 0.0, "the axis of a rotation must not be 0");
+        let [x, y, z] = axis.map(|c| c / length * (angle / 2.0).sin());
+        Quaternion {
+            w: (angle / 2.0).cos(),
+            x,
+            y,
+            z,
+        }
+    }
+
+    /// Scaled back to unit length, as rounding errors build up when rotations are combined
+    pub fn normalized(&self) -> Quaternion {
+        let length = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        Quaternion {
+            w: self.w / length,
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+
+    /// The opposite rotation
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    /// The rotation as a matrix, by rows, to multiply column vectors by
+    pub fn to_matrix(&self) -> [[f64; 3]; 3] {
+        let Quaternion { w, x, y, z } = self.normalized();
+        [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ]
+    }
+}
+
+/// `a * b` rotates by `b`, then by `a`
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        let (a, b) = (self, rhs);
+        Quaternion {
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        }
+    }
+}
+
+/// The order in which the rotations of Euler angles are applied, about the fixed axes of the
+/// world, so that `Yzx` turns about y first, then z, then x
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EulerOrder {
+    Xyz,
+    Xzy,
+    Yxz,
+    Yzx,
+    Zxy,
+    Zyx,
+}
+
+impl EulerOrder {
+    fn axes(&self) -> [Axis; 3] {
+        use Axis::*;
+        match self {
+            EulerOrder::Xyz => [X, Y, Z],
+            EulerOrder::Xzy => [X, Z, Y],
+            EulerOrder::Yxz => [Y, X, Z],
+            EulerOrder::Yzx => [Y, Z, X],
+            EulerOrder::Zxy => [Z, X, Y],
+            EulerOrder::Zyx => [Z, Y, X],
+        }
+    }
+}
+
+/// Which way an object faces
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    /// Rotations by `angles` about the x, y and z axes, applied in the given order
+    Euler {
+        angles: [Angle; 3],
+        order: EulerOrder,
+    },
+    AxisAngle {
+        axis: [f64; 3],
+        angle: Angle,
+    },
+    Quaternion(Quaternion),
+}
+
+impl Orientation {
+    pub const IDENTITY: Orientation = Orientation::Quaternion(Quaternion::IDENTITY);
+
+    pub fn to_quaternion(&self) -> Quaternion {
+        match self {
+            Orientation::Euler { angles, order } => {
+                order
+                    .axes()
+                    .iter()
+                    .fold(Quaternion::IDENTITY, |rotation, axis| {
+                        let (unit, angle) = match axis {
+                            Axis::X => ([1.0, 0.0, 0.0], angles[0]),
+                            Axis::Y => ([0.0, 1.0, 0.0], angles[1]),
+                            Axis::Z => ([0.0, 0.0, 1.0], angles[2]),
+                        };
+                        Quaternion::from_axis_angle(unit, angle) * rotation
+                    })
+            }
+            Orientation::AxisAngle { axis, angle } => Quaternion::from_axis_angle(*axis, *angle),
+            Orientation::Quaternion(quaternion) => quaternion.normalized(),
+        }
+    }
+}
+
+#[test]
+fn test_orientation() {
+    use std::f64::consts::PI;
+    let rotate = |orientation: Orientation, v: [f64; 3]| {
+        let matrix = orientation.to_quaternion().to_matrix();
+        matrix.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+    };
+    let close = |a: [f64; 3], b: [f64; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-12);
+
+    // rolling about x, which the old pair of angles could not do
+    let roll = Orientation::Euler {
+        angles: [PI / 2.0, 0.0, 0.0],
+        order: EulerOrder::Xyz,
+    };
+    assert!(close(rotate(roll, [0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]));
+    let axis_angle = Orientation::AxisAngle {
+        axis: [2.0, 0.0, 0.0],
+        angle: PI / 2.0,
+    };
+    assert!(close(rotate(axis_angle, [0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]));
+
+    // the order matters
+    let angles = [PI / 2.0, PI / 2.0, 0.0];
+    let xy = Orientation::Euler {
+        angles,
+        order: EulerOrder::Xyz,
+    };
+    let yx = Orientation::Euler {
+        angles,
+        order: EulerOrder::Yxz,
+    };
+    assert!(close(rotate(xy, [0.0, 1.0, 0.0]), [1.0, 0.0, 0.0]));
+    assert!(close(rotate(yx, [0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]));
+
+    // combining quaternions is the same as applying the rotations in turn
+    let about_y = Quaternion::from_axis_angle([0.0, 1.0, 0.0], PI / 2.0);
+    let about_x = Quaternion::from_axis_angle([1.0, 0.0, 0.0], PI / 2.0);
+    assert!(close(
+        rotate(Orientation::Quaternion(about_y * about_x), [0.0, 1.0, 0.0]),
+        rotate(xy, [0.0, 1.0, 0.0])
+    ));
+    let undone = Orientation::Quaternion(about_x.conjugate() * about_x);
+    assert!(close(rotate(undone, [1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]));
+
+    // about the diagonal, a third of a turn moves each axis onto the next
+    let diagonal = Orientation::AxisAngle {
+        axis: [1.0, 1.0, 1.0],
+        angle: 2.0 * PI / 3.0,
+    };
+    assert!(close(rotate(diagonal, [1.0, 0.0, 0.0]), [0.0, 1.0, 0.0]));
+}
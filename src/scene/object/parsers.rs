@@ -1,3 +1,4 @@
 extern crate nom;
 pub mod common;
+pub mod mtl;
 pub mod obj;
@@ -1,8 +1,8 @@
 use nom::{
     character::complete::{char, one_of},
-    combinator::{map, recognize},
+    combinator::{map, map_res, opt, recognize},
     multi::{many0, many1},
-    sequence::terminated,
+    sequence::{pair, terminated},
     IResult,
 };
 
@@ -29,9 +29,20 @@ pub fn parse_integer(input: &str) -> IResult<&str, usize> {
     map(decimal, string_to_usize)(input)
 }
 
+/// An integer with an optional minus sign
+pub fn parse_signed_integer(input: &str) -> IResult<&str, isize> {
+    map_res(recognize(pair(opt(char('-')), decimal)), |digits: &str| {
+        digits.replace('_', "").parse::<isize>()
+    })(input)
+}
+
 #[test]
 fn test_parse_integer() {
     assert_eq!(parse_integer("0\r\n"), Ok(("\r\n", 0)));
     assert_eq!(parse_integer("5 "), Ok((" ", 5)));
     assert_eq!(parse_integer("14000"), Ok(("", 14000)));
+    assert_eq!(parse_signed_integer("-3/"), Ok(("/", -3)));
+    assert_eq!(parse_signed_integer("12_000 "), Ok((" ", 12000)));
+    assert!(parse_signed_integer("- 3").is_err());
+    assert!(parse_signed_integer("99999999999999999999").is_err());
 }
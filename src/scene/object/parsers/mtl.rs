@@ -0,0 +1,330 @@
+/// Parse Wavefront .MTL material libraries
+mod statement;
+pub use self::statement::TextureMap;
+use self::statement::{
+    parse_colour, parse_dissolve, parse_illumination_model, parse_material_name, parse_scalar,
+    parse_texture_map,
+};
+
+use crate::{
+    image::{output::TransferFunction, Colour},
+    ppm::reader::read_from_ppm,
+    scene::object::material::{
+        texture::{ImageTexture, Texture},
+        Material, SpecularModel,
+    },
+};
+use nom::{
+    combinator::{all_consuming, map},
+    IResult,
+};
+use std::{fs, io, path::Path};
+
+/// The statements of one `newmtl` entry, before mapping onto a `Material`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialDefinition {
+    pub name: String,
+    pub ambient: [f64; 3],
+    pub diffuse: [f64; 3],
+    pub specular: [f64; 3],
+    pub shininess: f64,
+    /// Opacity, where 1 is fully opaque
+    pub dissolve: f64,
+    pub refractive_index: f64,
+    pub illumination_model: usize,
+    pub texture_maps: Vec<TextureMap>,
+}
+
+impl MaterialDefinition {
+    fn new(name: &str) -> MaterialDefinition {
+        MaterialDefinition {
+            name: name.to_string(),
+            ambient: [0.2; 3],
+            diffuse: [0.8; 3],
+            specular: [1.0; 3],
+            // an exponent of 0 would light the whole surface with a full strength highlight
+            shininess: Material::DEFAULT.shininess,
+            dissolve: 1.0,
+            refractive_index: 1.0,
+            illumination_model: 2,
+            texture_maps: vec![],
+        }
+    }
+
+    /// The file of the first texture map given by `statement`, e.g. "map_Kd"
+    pub fn texture_map(&self, statement: &str) -> Option<&str> {
+        self.texture_maps
+            .iter()
+            .find(|map| map.statement == statement)
+            .map(|map| map.filename.as_str())
+    }
+
+    /// The diffuse colour, scaled to a maximum component of 1
+    fn colour(&self) -> Colour {
+        let [red, green, blue] = self.diffuse;
+        let brightest = red.max(green).max(blue);
+        if brightest > 0.0 {
+            Colour { red, green, blue }.scale(1.0 / brightest)
+        } else {
+            Colour::WHITE
+        }
+    }
+
+    /// Map onto a `Material`: the diffuse colour, scaled to a maximum component of 1, becomes
+    /// the colour, and the strengths of Ka, Kd and Ks become the ambient, diffuse and specular
+    /// weights, which are normalised to sum to 1
+    pub fn to_material(&self) -> Material {
+        let average = |[r, g, b]: [f64; 3]| (r + g + b) / 3.0;
+        let brightest = |[r, g, b]: [f64; 3]| r.max(g).max(b);
+
+        let diffuse_strength = brightest(self.diffuse);
+
+        let (mut ambient, mut diffuse, mut specular) = (
+            average(self.ambient),
+            diffuse_strength,
+            average(self.specular),
+        );
+        match self.illumination_model {
+            0 => {
+                // colour on, lighting off
+                ambient = 1.0;
+                diffuse = 0.0;
+                specular = 0.0;
+            }
+            1 => specular = 0.0, // no highlights
+            _ => {}
+        }
+        let total = ambient + diffuse + specular;
+        let (ambient, diffuse, specular) = if total > 0.0 {
+            (ambient / total, diffuse / total, specular / total)
+        } else {
+            (1.0, 0.0, 0.0)
+        };
+
+        let transparency = (1.0 - self.dissolve).clamp(0.0, 1.0);
+        // illumination models 3 to 8 enable ray-traced or Fresnel reflections
+        let reflectivity = if (3..=8).contains(&self.illumination_model) {
+            average(self.specular).clamp(0.0, 1.0 - transparency)
+        } else {
+            0.0
+        };
+
+        Material {
+            ambient,
+            diffuse,
+            specular,
+            shininess: self.shininess,
+            specular_model: SpecularModel::Phong,
+            colour: Texture::Constant(self.colour()),
+            reflectivity,
+            transparency,
+            refractive_index: self.refractive_index,
+        }
+    }
+
+    /// Map onto a `Material` like `to_material`, with the diffuse texture map, if any, read
+    /// relative to `directory`. Only diffuse maps in sRGB encoded PPM images are supported, and
+    /// any other texture map is an error rather than being left out.
+    pub fn load_material(&self, directory: &Path) -> io::Result<Material> {
+        for map in self.texture_maps.iter() {
+            let message = if map.statement != "map_Kd" {
+                format!("{} texture maps are not supported", map.statement)
+            } else if !map.filename.to_lowercase().ends_with(".ppm") {
+                format!("{}: only PPM texture maps are supported", map.filename)
+            } else {
+                continue;
+            };
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("material {}: {}", self.name, message),
+            ));
+        }
+        let mut material = self.to_material();
+        if let Some(filename) = self.texture_map("map_Kd") {
+            material.colour = Texture::Tinted {
+                tint: self.colour(),
+                texture: Box::new(read_texture_map(&directory.join(filename))?),
+            };
+        }
+        Ok(material)
+    }
+}
+
+/// An image texture from an sRGB encoded PPM file, decoded to linear colours
+pub fn read_texture_map(path: &Path) -> io::Result<Texture> {
+    let mut image = read_from_ppm(&path.to_string_lossy())?;
+    let srgb = TransferFunction::Srgb;
+    image.pixels.mapv_inplace(|colour| Colour {
+        red: srgb.decode(colour.red),
+        green: srgb.decode(colour.green),
+        blue: srgb.decode(colour.blue),
+    });
+    Ok(Texture::Image(ImageTexture::new(image)))
+}
+
+enum Statement<'a> {
+    NewMaterial(&'a str),
+    Ambient([f64; 3]),
+    Diffuse([f64; 3]),
+    Specular([f64; 3]),
+    Shininess(f64),
+    Dissolve(f64),
+    Transparency(f64),
+    RefractiveIndex(f64),
+    IlluminationModel(usize),
+    TextureMap(TextureMap),
+    /// A keyword which is not part of the format
+    Unknown(&'a str),
+}
+
+/// Parse one statement, or None for statements that are recognised but not supported
+fn parse_statement(input: &str) -> IResult<&str, Option<Statement<'_>>> {
+    let keyword = input.split_whitespace().next().unwrap_or_default();
+    let colour = |tag_string| move |input| parse_colour(tag_string, input);
+    let scalar = |tag_string| move |input| parse_scalar(tag_string, input);
+    match keyword {
+        "newmtl" => map(parse_material_name, |name| {
+            Some(Statement::NewMaterial(name))
+        })(input),
+        "Ka" => map(colour("Ka"), |c| Some(Statement::Ambient(c)))(input),
+        "Kd" => map(colour("Kd"), |c| Some(Statement::Diffuse(c)))(input),
+        "Ks" => map(colour("Ks"), |c| Some(Statement::Specular(c)))(input),
+        "Ns" => map(scalar("Ns"), |n| Some(Statement::Shininess(n)))(input),
+        "d" => map(parse_dissolve, |d| Some(Statement::Dissolve(d)))(input),
+        "Tr" => map(scalar("Tr"), |t| Some(Statement::Transparency(t)))(input),
+        "Ni" => map(scalar("Ni"), |n| Some(Statement::RefractiveIndex(n)))(input),
+        "illum" => map(parse_illumination_model, |model| {
+            Some(Statement::IlluminationModel(model))
+        })(input),
+        "bump" | "disp" | "decal" | "refl" | "norm" => {
+            map(parse_texture_map, |map| Some(Statement::TextureMap(map)))(input)
+        }
+        _ if keyword.starts_with("map_") => {
+            map(parse_texture_map, |map| Some(Statement::TextureMap(map)))(input)
+        }
+        // emission, transmission filter, reflection sharpness and the physically based
+        // extensions
+        "Ke" | "Tf" | "sharpness" | "Pr" | "Pm" | "Ps" | "Pc" | "Pcr" | "aniso" | "anisor" => {
+            Ok(("", None))
+        }
+        _ => Ok(("", Some(Statement::Unknown(keyword)))),
+    }
+}
+
+pub fn parse_materials(input: &str) -> Result<Vec<MaterialDefinition>, String> {
+    let mut materials: Vec<MaterialDefinition> = vec![];
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| format!("line {}: {}", line_index + 1, message);
+        let statement = match all_consuming(parse_statement)(line) {
+            Ok((_, Some(statement))) => statement,
+            Ok((_, None)) => continue,
+            Err(_) => return Err(error("invalid statement")),
+        };
+        if let Statement::NewMaterial(name) = statement {
+            materials.push(MaterialDefinition::new(name));
+            continue;
+        }
+        let material = materials
+            .last_mut()
+            .ok_or_else(|| error("statement before the first newmtl"))?;
+        match statement {
+            Statement::NewMaterial(_) => {} // handled above
+            Statement::Unknown(keyword) => {
+                return Err(error(&format!("unknown statement \"{}\"", keyword)))
+            }
+            Statement::Ambient(colour) => material.ambient = colour,
+            Statement::Diffuse(colour) => material.diffuse = colour,
+            Statement::Specular(colour) => material.specular = colour,
+            Statement::Shininess(shininess) => material.shininess = shininess,
+            Statement::Dissolve(dissolve) => material.dissolve = dissolve,
+            Statement::Transparency(transparency) => material.dissolve = 1.0 - transparency,
+            Statement::RefractiveIndex(index) => material.refractive_index = index,
+            Statement::IlluminationModel(model) => material.illumination_model = model,
+            Statement::TextureMap(map) => material.texture_maps.push(map),
+        }
+    }
+    Ok(materials)
+}
+
+pub fn parse_file(filename: &str) -> io::Result<Vec<MaterialDefinition>> {
+    let input = fs::read_to_string(filename)?;
+    parse_materials(&input).map_err(|message| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", filename, message),
+        )
+    })
+}
+
+#[test]
+fn test_parse_materials() {
+    let materials = parse_materials(
+        "# two materials\r\n\
+         newmtl red\r\n\
+         Ka 0.1 0.0 0.0\r\n\
+         Kd 0.8 0.2 0.2\r\n\
+         Ks 0.3\r\n\
+         Ns 32\r\n\
+         illum 2\r\n\
+         map_Kd -s 2 2 1 red.ppm\r\n\
+         \r\n\
+         newmtl glass\r\n\
+         Tr 0.9\r\n\
+         Ni 1.5\r\n\
+         Ke 0 0 0\r\n\
+         illum 7\r\n",
+    )
+    .unwrap();
+    assert_eq!(materials.len(), 2);
+    let red = &materials[0];
+    assert_eq!(red.name, "red");
+    assert_eq!(red.diffuse, [0.8, 0.2, 0.2]);
+    assert_eq!(red.specular, [0.3, 0.3, 0.3]);
+    assert_eq!(red.texture_map("map_Kd"), Some("red.ppm"));
+    assert_eq!(red.texture_map("map_Ks"), None);
+
+    assert_eq!(red.colour().green, 0.25);
+    let material = red.to_material();
+    assert!((material.ambient + material.diffuse + material.specular - 1.0).abs() < 1e-12);
+    assert_eq!(material.shininess, 32.0);
+    assert_eq!(material.reflectivity, 0.0);
+
+    let glass = materials[1].to_material();
+    assert_eq!(glass.shininess, Material::DEFAULT.shininess);
+    assert!((glass.transparency - 0.9).abs() < 1e-12);
+    assert_eq!(glass.refractive_index, 1.5);
+    assert!((glass.reflectivity - 0.1).abs() < 1e-12);
+
+    assert_eq!(
+        parse_materials("Kd 1 1 1\n").err(),
+        Some("line 1: statement before the first newmtl".to_string())
+    );
+    assert_eq!(
+        parse_materials("newmtl a\nNs high\n").err(),
+        Some("line 2: invalid statement".to_string())
+    );
+    assert_eq!(
+        parse_materials("newmtl a\nKx 1 1 1\n").err(),
+        Some("line 2: unknown statement \"Kx\"".to_string())
+    );
+
+    // texture maps which would be left out are reported instead
+    let load = |input: &str| {
+        parse_materials(input).unwrap()[0]
+            .load_material(Path::new(""))
+            .map_err(|error| error.to_string())
+    };
+    assert_eq!(
+        load("newmtl a\nmap_Bump a.ppm\n").err(),
+        Some("material a: map_Bump texture maps are not supported".to_string())
+    );
+    assert_eq!(
+        load("newmtl a\nmap_Kd a.png\n").err(),
+        Some("material a: a.png: only PPM texture maps are supported".to_string())
+    );
+    assert!(load("newmtl a\nKd 1 0 0\n").is_ok());
+}
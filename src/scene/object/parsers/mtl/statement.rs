@@ -0,0 +1,118 @@
+use crate::scene::object::parsers::common::{
+    float::parse_float, integer::parse_integer, whitespace::tws,
+};
+use nom::{
+    bytes::complete::{is_not, tag, take_while1},
+    combinator::{map, opt},
+    sequence::{preceded, tuple},
+    IResult,
+};
+
+/// Parse an RGB colour statement such as "Kd 0.8 0.2 0.2", where a lone value is used for all
+/// three components
+pub fn parse_colour<'a>(tag_string: &'static str, input: &'a str) -> IResult<&'a str, [f64; 3]> {
+    let (input, (red, green_blue)) = preceded(
+        tws(tag(tag_string)),
+        tuple((
+            tws(parse_float),
+            opt(tuple((tws(parse_float), tws(parse_float)))),
+        )),
+    )(input)?;
+    let (green, blue) = green_blue.unwrap_or((red, red));
+    Ok((input, [red, green, blue]))
+}
+
+#[test]
+fn test_parse_colour() {
+    assert_eq!(
+        parse_colour("Kd", "Kd 0.8 0.2 0.1\r\n"),
+        Ok(("", [0.8, 0.2, 0.1]))
+    );
+    assert_eq!(parse_colour("Ka", "Ka 0.5"), Ok(("", [0.5, 0.5, 0.5])));
+    assert!(parse_colour("Ks", "Kd 0.8 0.2 0.1").is_err());
+    assert!(parse_colour("Ks", "Ks spectral file.rfl").is_err());
+}
+
+pub fn parse_scalar<'a>(tag_string: &'static str, input: &'a str) -> IResult<&'a str, f64> {
+    preceded(tws(tag(tag_string)), tws(parse_float))(input)
+}
+
+/// Parse a dissolve statement, ignoring the "-halo" option
+pub fn parse_dissolve(input: &str) -> IResult<&str, f64> {
+    preceded(
+        tuple((tws(tag("d")), opt(tws(tag("-halo"))))),
+        tws(parse_float),
+    )(input)
+}
+
+#[test]
+fn test_parse_dissolve() {
+    assert_eq!(parse_dissolve("d 0.5\n"), Ok(("", 0.5)));
+    assert_eq!(parse_dissolve("d -halo 0.25"), Ok(("", 0.25)));
+    assert!(parse_dissolve("Tr 0.5").is_err());
+}
+
+pub fn parse_illumination_model(input: &str) -> IResult<&str, usize> {
+    preceded(tws(tag("illum")), tws(parse_integer))(input)
+}
+
+/// A texture statement such as "map_Kd", with the file it refers to
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureMap {
+    pub statement: String,
+    pub filename: String,
+}
+
+/// Parse a texture statement, skipping any options between the statement and the filename
+pub fn parse_texture_map(input: &str) -> IResult<&str, TextureMap> {
+    let (input, (statement, arguments)) = tuple((
+        tws(take_while1(|c: char| c.is_alphanumeric() || c == '_')),
+        tws(is_not("\r\n")),
+    ))(input)?;
+    let filename = arguments.split_whitespace().last().unwrap_or_default(); // is_not is never empty
+    Ok((
+        input,
+        TextureMap {
+            statement: statement.to_string(),
+            filename: filename.to_string(),
+        },
+    ))
+}
+
+#[test]
+fn test_parse_texture_map() {
+    assert_eq!(
+        parse_texture_map("map_Kd wood.ppm\r\n"),
+        Ok((
+            "",
+            TextureMap {
+                statement: "map_Kd".to_string(),
+                filename: "wood.ppm".to_string(),
+            }
+        ))
+    );
+    assert_eq!(
+        parse_texture_map("bump -bm 0.5 -clamp on textures/bump.ppm"),
+        Ok((
+            "",
+            TextureMap {
+                statement: "bump".to_string(),
+                filename: "textures/bump.ppm".to_string(),
+            }
+        ))
+    );
+    assert!(parse_texture_map("map_Kd\n").is_err());
+}
+
+pub fn parse_material_name(input: &str) -> IResult<&str, &str> {
+    map(
+        preceded(tws(tag("newmtl")), tws(is_not("\r\n"))),
+        str::trim_end,
+    )(input)
+}
+
+#[test]
+fn test_parse_material_name() {
+    assert_eq!(parse_material_name("newmtl red \r\n"), Ok(("", "red")));
+    assert!(parse_material_name("newmtl\r\n").is_err());
+}
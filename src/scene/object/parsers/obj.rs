@@ -1,27 +1,566 @@
 /// Parse Wavefront .OBJ files
 mod polygon;
-use self::polygon::Polygon;
+use self::polygon::{
+    parse_polygon_indices,
+    texture_coordinates::{parse_texture_coordinates, TextureCoordinates},
+    vector::{parse_vector, Vector},
+    vertex::{parse_vertex, Vertex},
+    Polygon, PolygonIndices,
+};
 mod material;
-//use material::Material;
+use self::material::{
+    filename::parse_material_filenames,
+    identifier::parse_material_identifier,
+    map::{parse_map_filenames, parse_texture_map_identifier},
+};
+
+use crate::scene::object::{
+    bvh::BoundingVolumeHierarchy,
+    material::{texture::Texture, Material},
+    matrix::AffineTransformation,
+    parsers::{common::whitespace::tws, mtl},
+    LeafObject, Object, ObjectShape, TexturedObject,
+};
+use nom::{
+    bytes::complete::{is_not, tag},
+    combinator::{all_consuming, map, opt},
+    sequence::preceded,
+    IResult,
+};
+use std::{collections::HashMap, fs, io, path::Path};
 
 struct Group {
-    //material: Material,
+    /// Name given by the `usemtl` statement in effect
+    material: Option<String>,
+    /// Name given by the `usemap` statement in effect, unless it turned maps off
+    map: Option<String>,
     polygons: Vec<Polygon>,
 }
 
+impl Group {
+    fn new(material: Option<String>, map: Option<String>) -> Group {
+        Group {
+            material,
+            map,
+            polygons: vec![],
+        }
+    }
+
+    fn to_mesh(&self) -> ObjectShape {
+        let triangles = self
+            .polygons
+            .iter()
+            .flat_map(|polygon| polygon.to_triangles())
+            .map(LeafObject::new)
+            .collect();
+        ObjectShape::Mesh(BoundingVolumeHierarchy::new(triangles))
+    }
+}
+
 pub struct WaveFrontObject {
     name: Option<String>,
     groups: Vec<Group>,
+    /// Files named by `mtllib` statements up to the end of this object
+    material_libraries: Vec<String>,
+    /// Materials from the libraries, by name
+    materials: HashMap<String, Material>,
+    /// Image files named by `maplib` statements up to the end of this object
+    map_libraries: Vec<String>,
+    /// Texture maps from the map libraries, by file name without its extension
+    maps: HashMap<String, Texture>,
 }
 
 impl WaveFrontObject {
-    pub fn to_object(&self) {
-        // TODO
+    fn new(name: Option<String>, material: Option<String>, map: Option<String>) -> WaveFrontObject {
+        WaveFrontObject {
+            name,
+            groups: vec![Group::new(material, map)],
+            material_libraries: vec![],
+            materials: HashMap::new(),
+            map_libraries: vec![],
+            maps: HashMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.groups
+            .iter()
+            .flat_map(|group| group.polygons.iter())
+            .map(|polygon| polygon.to_triangles().len())
+            .sum()
+    }
+
+    pub fn set_materials(&mut self, materials: HashMap<String, Material>) {
+        self.materials = materials;
+    }
+
+    pub fn set_maps(&mut self, maps: HashMap<String, Texture>) {
+        self.maps = maps;
+    }
+
+    fn get_material(&self, group: &Group) -> Option<&Material> {
+        group
+            .material
+            .as_ref()
+            .and_then(|name| self.materials.get(name))
+    }
+
+    fn get_map(&self, group: &Group) -> Option<&Texture> {
+        group.map.as_ref().and_then(|name| self.maps.get(name))
+    }
+
+    /// The group's material, or `fallback`, with its colour replaced by the group's texture
+    /// map, if it has one
+    fn group_material(&self, group: &Group, fallback: Option<&Material>) -> Option<Material> {
+        let material = self.get_material(group).or(fallback);
+        match self.get_map(group) {
+            Some(map) => Some(Material {
+                colour: map.clone(),
+                ..material.cloned().unwrap_or_default()
+            }),
+            None => material.cloned(),
+        }
+    }
+
+    /// Build a triangle mesh object, placed and coloured like `Object::new`. Groups using a
+    /// known material or texture map become separate meshes in a `GroupedMesh`, while the rest
+    /// get `material`.
+    pub fn to_object(
+        &self,
+        transformation: Option<AffineTransformation>,
+        material: Option<Material>,
+    ) -> Object {
+        let groups = self
+            .groups
+            .iter()
+            .filter(|group| !group.polygons.is_empty());
+        if groups
+            .clone()
+            .all(|group| self.get_material(group).is_none() && self.get_map(group).is_none())
+        {
+            let triangles = groups
+                .flat_map(|group| group.polygons.iter())
+                .flat_map(|polygon| polygon.to_triangles())
+                .map(LeafObject::new)
+                .collect();
+            return Object::new(
+                ObjectShape::Mesh(BoundingVolumeHierarchy::new(triangles)),
+                transformation,
+                material,
+            );
+        }
+        let children = groups
+            .map(|group| {
+                TexturedObject::new(
+                    group.to_mesh(),
+                    self.group_material(group, material.as_ref()),
+                )
+            })
+            .collect();
+        Object::new(
+            ObjectShape::GroupedMesh(BoundingVolumeHierarchy::new(children)),
+            transformation,
+            material,
+        )
     }
 }
 
-fn parse_file(filename: &str) -> Vec<WaveFrontObject> {
-    let mut result = vec![];
-    // TODO
-    result
+enum Statement<'a> {
+    Vertex(Vertex),
+    TextureCoordinates(TextureCoordinates),
+    Normal(Vector),
+    Face(PolygonIndices),
+    Object(&'a str),
+    Group,
+    MaterialLibraries(Vec<&'a str>),
+    UseMaterial(&'a str),
+    MapLibraries(Vec<&'a str>),
+    /// A texture map's name, or None for "off"
+    UseMap(Option<&'a str>),
+    /// A keyword which is not part of the format
+    Unknown(&'a str),
+}
+
+fn parse_object_name(input: &str) -> IResult<&str, &str> {
+    preceded(tws(tag("o")), tws(is_not("\r\n")))(input)
+}
+
+fn parse_group_names(input: &str) -> IResult<&str, Option<&str>> {
+    preceded(tws(tag("g")), opt(tws(is_not("\r\n"))))(input)
+}
+
+/// Parse one statement, or None for statements that are recognised but not supported
+fn parse_statement(input: &str) -> IResult<&str, Option<Statement<'_>>> {
+    let keyword = input.split_whitespace().next().unwrap_or_default();
+    match keyword {
+        "v" => map(parse_vertex, |v| Some(Statement::Vertex(v)))(input),
+        "vt" => map(parse_texture_coordinates, |vt| {
+            Some(Statement::TextureCoordinates(vt))
+        })(input),
+        "vn" => map(parse_vector, |vn| Some(Statement::Normal(vn)))(input),
+        "f" => map(parse_polygon_indices, |f| Some(Statement::Face(f)))(input),
+        "o" => map(parse_object_name, |o| Some(Statement::Object(o)))(input),
+        "g" => map(parse_group_names, |_| Some(Statement::Group))(input),
+        "mtllib" => map(parse_material_filenames, |filenames| {
+            Some(Statement::MaterialLibraries(filenames))
+        })(input),
+        "usemtl" => map(parse_material_identifier, |name| {
+            Some(Statement::UseMaterial(name))
+        })(input),
+        "maplib" => map(parse_map_filenames, |filenames| {
+            Some(Statement::MapLibraries(filenames))
+        })(input),
+        "usemap" => map(parse_texture_map_identifier, |name| {
+            Some(Statement::UseMap(Some(name).filter(|&name| name != "off")))
+        })(input),
+        // smoothing groups, points, lines, free-form geometry and display attributes
+        "s" | "mg" | "p" | "l" | "vp" | "cstype" | "deg" | "bmat" | "step" | "curv" | "curv2"
+        | "surf" | "parm" | "trim" | "hole" | "scrv" | "sp" | "end" | "con" | "bevel"
+        | "c_interp" | "d_interp" | "lod" | "shadow_obj" | "trace_obj" | "ctech" | "stech"
+        | "call" | "csh" => Ok(("", None)),
+        _ => Ok(("", Some(Statement::Unknown(keyword)))),
+    }
+}
+
+pub fn parse_objects(input: &str) -> Result<Vec<WaveFrontObject>, String> {
+    let mut vertices = vec![];
+    let mut texture_coordinates = vec![];
+    let mut normals = vec![];
+    let mut material_libraries = vec![];
+    let mut current_material: Option<String> = None;
+    let mut map_libraries = vec![];
+    let mut current_map: Option<String> = None;
+    let mut objects = vec![WaveFrontObject::new(None, None, None)];
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| format!("line {}: {}", line_index + 1, message);
+        let statement = match all_consuming(parse_statement)(line) {
+            Ok((_, statement)) => statement,
+            Err(_) => return Err(error("invalid statement")),
+        };
+        let object = objects.last_mut().unwrap(); // never empty
+        match statement {
+            Some(Statement::Vertex(vertex)) => vertices.push(vertex),
+            Some(Statement::TextureCoordinates(coordinates)) => {
+                texture_coordinates.push(coordinates)
+            }
+            Some(Statement::Normal(normal)) => normals.push(normal),
+            Some(Statement::Face(indices)) => {
+                let polygon = indices
+                    .to_polygon(&vertices, &texture_coordinates, &normals)
+                    .map_err(error)?;
+                object.groups.last_mut().unwrap().polygons.push(polygon); // never empty
+            }
+            Some(Statement::Object(name)) => {
+                object.material_libraries = material_libraries.clone();
+                object.map_libraries = map_libraries.clone();
+                objects.push(WaveFrontObject::new(
+                    Some(name.to_string()),
+                    current_material.clone(),
+                    current_map.clone(),
+                ))
+            }
+            Some(Statement::Group) => object
+                .groups
+                .push(Group::new(current_material.clone(), current_map.clone())),
+            Some(Statement::MaterialLibraries(filenames)) => {
+                material_libraries.extend(filenames.into_iter().map(String::from))
+            }
+            Some(Statement::UseMaterial(name)) => {
+                current_material = Some(name.to_string());
+                object
+                    .groups
+                    .push(Group::new(current_material.clone(), current_map.clone()));
+            }
+            Some(Statement::MapLibraries(filenames)) => {
+                map_libraries.extend(filenames.into_iter().map(String::from))
+            }
+            Some(Statement::UseMap(name)) => {
+                current_map = name.map(String::from);
+                object
+                    .groups
+                    .push(Group::new(current_material.clone(), current_map.clone()));
+            }
+            Some(Statement::Unknown(keyword)) => {
+                return Err(error(&format!("unknown statement \"{}\"", keyword)))
+            }
+            None => {}
+        }
+    }
+    let last = objects.last_mut().unwrap(); // never empty
+    last.material_libraries = material_libraries;
+    last.map_libraries = map_libraries;
+    objects.retain(|object| object.groups.iter().any(|group| !group.polygons.is_empty()));
+    Ok(objects)
+}
+
+/// Parse an .OBJ file, loading the material and texture map libraries it names from the same
+/// directory
+pub fn parse_file(filename: &str) -> io::Result<Vec<WaveFrontObject>> {
+    let input = fs::read_to_string(filename)?;
+    let mut objects = parse_objects(&input).map_err(|message| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", filename, message),
+        )
+    })?;
+    let directory = Path::new(filename)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let mut libraries: HashMap<&str, HashMap<String, Material>> = HashMap::new();
+    for object in objects.iter() {
+        for library in object.material_libraries.iter() {
+            if libraries.contains_key(library.as_str()) {
+                continue;
+            }
+            let path = directory.join(library);
+            let definitions = mtl::parse_file(&path.to_string_lossy())?;
+            let materials = definitions
+                .iter()
+                .map(|definition| {
+                    Ok((
+                        definition.name.clone(),
+                        definition.load_material(directory)?,
+                    ))
+                })
+                .collect::<io::Result<_>>()?;
+            libraries.insert(library, materials);
+        }
+    }
+    let materials: Vec<HashMap<String, Material>> = objects
+        .iter()
+        .map(|object| {
+            // later libraries take precedence
+            let mut materials = HashMap::new();
+            for library in object.material_libraries.iter() {
+                materials.extend(libraries[library.as_str()].clone());
+            }
+            materials
+        })
+        .collect();
+    for (object, materials) in objects.iter_mut().zip(materials) {
+        object.set_materials(materials);
+    }
+
+    let mut maps: HashMap<String, (String, Texture)> = HashMap::new();
+    for object in objects.iter() {
+        for library in object.map_libraries.iter() {
+            if maps.contains_key(library) {
+                continue;
+            }
+            let path = directory.join(library);
+            let name = Path::new(library)
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            maps.insert(library.clone(), (name, mtl::read_texture_map(&path)?));
+        }
+    }
+    for object in objects.iter_mut() {
+        let object_maps = object
+            .map_libraries
+            .iter()
+            .map(|library| maps[library].clone())
+            .collect();
+        object.set_maps(object_maps);
+    }
+    Ok(objects)
+}
+
+#[test]
+fn test_parse_objects() {
+    let objects = parse_objects(
+        "# two objects sharing vertices\r\n\
+         mtllib cube.mtl\r\n\
+         v 0 0 0\r\n\
+         v 1 0 0\r\n\
+         v 1 1 0\r\n\
+         v 0 1 0\r\n\
+         vt 0 0\r\n\
+         vn 0 0 1\r\n\
+         o square\r\n\
+         g front\r\n\
+         usemtl red\r\n\
+         s off\r\n\
+         f 1/1/1 2/1/1 3/1/1 4/1/1\r\n\
+         o triangle\r\n\
+         f 1 2 3\r\n\
+         o empty\r\n",
+    )
+    .unwrap();
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects[0].name(), Some("square"));
+    assert_eq!(objects[0].triangle_count(), 2);
+    assert_eq!(objects[1].name(), Some("triangle"));
+    assert_eq!(objects[1].triangle_count(), 1);
+
+    assert_eq!(
+        parse_objects("v 0 0 0\nf 1 2 3\n").err(),
+        Some("line 2: vertex index out of range".to_string())
+    );
+    assert_eq!(
+        parse_objects("v 0 0\n").err(),
+        Some("line 1: invalid statement".to_string())
+    );
+    assert_eq!(
+        parse_objects("v 0 0 0\nvx 1 2 3\n").err(),
+        Some("line 2: unknown statement \"vx\"".to_string())
+    );
+
+    // negative indices count back from the latest vertex, texture coordinates and normal
+    let objects = parse_objects(
+        "v 5 5 5\n\
+         v 0 0 0\n\
+         v 1 0 0\n\
+         v 0 1 0\n\
+         vn 0 0 1\n\
+         f -3//-1 -2//-1 -1//-1\n\
+         v 1 1 0\n\
+         f -4 -3 -1 -2\n",
+    )
+    .unwrap();
+    assert_eq!(objects[0].triangle_count(), 3);
+    assert_eq!(
+        parse_objects("v 0 0 0\nf -1 -2 -3\n").err(),
+        Some("line 2: vertex index out of range".to_string())
+    );
+}
+
+/// A directory of test files, unique to the test and process, removed when dropped
+#[cfg(test)]
+struct TestDirectory(std::path::PathBuf);
+
+#[cfg(test)]
+impl TestDirectory {
+    fn new(name: &str) -> TestDirectory {
+        let path = std::env::temp_dir().join(format!("ray_tracer_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        TestDirectory(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_parse_file_with_materials() {
+    let directory = TestDirectory::new("parse_file_with_materials");
+    let directory = directory.0.as_path();
+    fs::write(
+        directory.join("colours.mtl"),
+        "newmtl red\nKd 1 0 0\nnewmtl green\nKd 0 1 0\nmap_Kd stripes.ppm\n",
+    )
+    .unwrap();
+    fs::write(
+        directory.join("stripes.ppm"),
+        "P3 2 1 255 255 255 255 0 0 0\n",
+    )
+    .unwrap();
+    fs::write(
+        directory.join("squares.obj"),
+        "mtllib colours.mtl\n\
+         v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         o squares\n\
+         usemtl red\nf 1 2 3 4\n\
+         g back\nf 4 3 2 1\n\
+         usemtl missing\nf 1 2 3\n\
+         usemtl green\nf 1 3 4\n",
+    )
+    .unwrap();
+    let objects = parse_file(&directory.join("squares.obj").to_string_lossy()).unwrap();
+    let origin = crate::vector::Point::new([0.0; 3]);
+    assert_eq!(objects.len(), 1);
+    let object = &objects[0];
+    let group_materials: Vec<Option<&str>> = object
+        .groups
+        .iter()
+        .filter(|group| !group.polygons.is_empty())
+        .map(|group| group.material.as_deref())
+        .collect();
+    assert_eq!(
+        group_materials,
+        vec![Some("red"), Some("red"), Some("missing"), Some("green")]
+    );
+    assert_eq!(
+        object
+            .get_material(&object.groups[1])
+            .unwrap()
+            .colour
+            .get_colour([0.0; 2], &origin)
+            .red,
+        1.0
+    );
+    assert!(object.get_material(&object.groups[3]).is_none());
+    let green = object.get_material(&object.groups[4]).unwrap();
+    assert_eq!(green.colour.get_colour([0.25, 0.5], &origin).green, 1.0);
+    assert_eq!(green.colour.get_colour([0.25, 0.5], &origin).red, 0.0);
+    assert!(matches!(
+        object.to_object(None, None).shape,
+        ObjectShape::GroupedMesh(_)
+    ));
+
+    fs::write(
+        directory.join("broken.obj"),
+        "mtllib absent.mtl\nv 0 0 0\nf 1 1 1\n",
+    )
+    .unwrap();
+    assert!(parse_file(&directory.join("broken.obj").to_string_lossy()).is_err());
+}
+
+#[test]
+fn test_parse_file_with_texture_maps() {
+    let directory = TestDirectory::new("parse_file_with_texture_maps");
+    let directory = directory.0.as_path();
+    fs::write(
+        directory.join("checker.ppm"),
+        "P3 2 1 255 255 255 255 0 0 0\n",
+    )
+    .unwrap();
+    fs::write(
+        directory.join("maps.obj"),
+        "maplib checker.ppm\n\
+         v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         usemap checker\nf 1 2 3\n\
+         usemap off\nf 1 3 4\n",
+    )
+    .unwrap();
+    let objects = parse_file(&directory.join("maps.obj").to_string_lossy()).unwrap();
+    let object = &objects[0];
+    let groups: Vec<&Group> = object
+        .groups
+        .iter()
+        .filter(|group| !group.polygons.is_empty())
+        .collect();
+    assert_eq!(groups[0].map.as_deref(), Some("checker"));
+    assert_eq!(groups[1].map, None);
+
+    // the map takes the place of the fallback material's colour
+    let origin = crate::vector::Point::new([0.0; 3]);
+    let fallback = Material::new(1.0, 0.0, 0.0, 1.0, crate::image::Colour::BLACK);
+    let mapped = object.group_material(groups[0], Some(&fallback)).unwrap();
+    assert_eq!(mapped.ambient, 1.0);
+    assert_eq!(mapped.colour.get_colour([0.25, 0.5], &origin).red, 1.0);
+    assert_eq!(mapped.colour.get_colour([0.75, 0.5], &origin).red, 0.0);
+    assert!(object.group_material(groups[1], None).is_none());
+    assert!(matches!(
+        object.to_object(None, None).shape,
+        ObjectShape::GroupedMesh(_)
+    ));
+
+    fs::write(
+        directory.join("broken.obj"),
+        "maplib absent.ppm\nv 0 0 0\nf 1 1 1\n",
+    )
+    .unwrap();
+    assert!(parse_file(&directory.join("broken.obj").to_string_lossy()).is_err());
 }
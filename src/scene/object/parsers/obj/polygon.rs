@@ -1,4 +1,10 @@
-use crate::scene::object::parsers::common::{integer::parse_integer, whitespace::tws};
+use crate::{
+    scene::object::{
+        parsers::common::{integer::parse_signed_integer, whitespace::tws},
+        MappedTriangle, MappedTrianglePoint, ObjectShape,
+    },
+    vector::Normal,
+};
 use nom::{
     branch::alt,
     bytes::complete::tag,
@@ -8,36 +14,39 @@ use nom::{
     IResult,
 };
 
-mod vertex;
+pub mod vertex;
 use self::vertex::Vertex;
 
-mod vector;
+pub mod vector;
 use self::vector::Vector;
 
-mod texture_coordinates;
+pub mod texture_coordinates;
 use self::texture_coordinates::TextureCoordinates;
 
+mod triangulation;
+use self::triangulation::triangulate;
+
 #[derive(Debug, PartialEq)]
+/// 1-based indices into the lists read so far, or relative to their ends if negative
 pub struct PolygonVertexIndices {
-    vertex: usize,
-    texture_coordinates: Option<usize>,
-    normal: Option<usize>,
+    vertex: isize,
+    texture_coordinates: Option<isize>,
+    normal: Option<isize>,
 }
 
 fn parse_polygon_vertex_indices(input: &str) -> IResult<&str, PolygonVertexIndices> {
     let (input, (vertex, texture_coordinates, normal)) = alt((
         tuple((
-            parse_integer,
+            parse_signed_integer,
             value(None, tag("/")),
-            preceded(tag("/"), map(parse_integer, |i| Some(i))),
+            preceded(tag("/"), map(parse_signed_integer, |i| Some(i))),
         )),
         tuple((
-            parse_integer,
-            opt(preceded(tag("/"), parse_integer)),
-            opt(preceded(tag("/"), parse_integer)),
+            parse_signed_integer,
+            opt(preceded(tag("/"), parse_signed_integer)),
+            opt(preceded(tag("/"), parse_signed_integer)),
         )),
     ))(input)?;
-    // TODO: if 0 -> ERROR
 
     Ok((
         input,
@@ -95,6 +104,17 @@ fn test_parse_polygon_vertex_indices() {
             }
         ))
     );
+    assert_eq!(
+        parse_polygon_vertex_indices("-4/-1"),
+        Ok((
+            "",
+            PolygonVertexIndices {
+                vertex: -4,
+                texture_coordinates: Some(-1),
+                normal: None,
+            }
+        ))
+    );
     assert!(parse_polygon_vertex_indices("/1").is_err());
 }
 
@@ -103,9 +123,60 @@ pub struct PolygonIndices {
     points: Vec<PolygonVertexIndices>,
 }
 
+/// Look up a 1-based OBJ index, or one counting back from the end of the list if negative,
+/// so that -1 is the last element; 0 is never valid
+fn get_indexed<T: Copy>(list: &[T], index: isize) -> Option<T> {
+    let i = if index < 0 {
+        list.len().checked_sub(index.unsigned_abs())?
+    } else {
+        index.unsigned_abs().checked_sub(1)?
+    };
+    list.get(i).copied()
+}
+
 impl PolygonIndices {
-    pub fn to_polygon(&self) -> Result<Polygon, &'static str> {
-        Err("Not implemented") // TODO: take refs to lists of vertices, textures, normals
+    pub fn to_polygon(
+        &self,
+        vertices: &[Vertex],
+        texture_coordinates: &[TextureCoordinates],
+        normals: &[Vector],
+    ) -> Result<Polygon, &'static str> {
+        if self.points.len() < 3 {
+            return Err("polygon has fewer than 3 vertices");
+        }
+        let mut points = vec![];
+        for indices in self.points.iter() {
+            let vertex =
+                get_indexed(vertices, indices.vertex).ok_or("vertex index out of range")?;
+            let texture_coordinates = match indices.texture_coordinates {
+                Some(index) => Some(
+                    get_indexed(texture_coordinates, index)
+                        .ok_or("texture coordinates index out of range")?,
+                ),
+                None => None,
+            };
+            let normal = match indices.normal {
+                Some(index) => {
+                    Some(get_indexed(normals, index).ok_or("normal index out of range")?)
+                }
+                None => None,
+            };
+            points.push(PolygonVertex {
+                vertex,
+                texture_coordinates,
+                normal,
+            });
+        }
+        let triangles = if points.len() == 3 {
+            vec![[0, 1, 2]]
+        } else {
+            let positions: Vec<[f64; 3]> = points
+                .iter()
+                .map(|point| point.vertex.to_point().to_array())
+                .collect();
+            triangulate(&positions)?
+        };
+        Ok(Polygon { points, triangles })
     }
 }
 
@@ -138,23 +209,140 @@ fn test_parse_polygon_indices() {
     )
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct PolygonVertex {
     vertex: Vertex,
-    texture_coordinates: TextureCoordinates,
-    normal: Vector,
+    texture_coordinates: Option<TextureCoordinates>,
+    normal: Option<Vector>,
 }
 
+#[derive(Debug, PartialEq)]
 pub struct Polygon {
     points: Vec<PolygonVertex>,
+    /// Corner indices of the triangles the polygon splits into
+    triangles: Vec<[usize; 3]>,
 }
 
-/*
 impl Polygon {
-    pub fn to_triangles(&self) -> Vec<Triangle> {
-        let mut result = vec![];
-        // TODO: split intelligently
-        result
+    /// Split into triangles which keep the texture coordinates and normals of their corners
+    pub fn triangulate(&self) -> Vec<[PolygonVertex; 3]> {
+        self.triangles
+            .iter()
+            .map(|corners| corners.map(|i| self.points[i]))
+            .collect()
     }
+
+    /// Triangles with texture coordinates or normals at any corner are mapped, so that they
+    /// shade smoothly. Their other corners, and those whose normal is zero, get the flat face
+    /// normal, or texture coordinates spanning the unit triangle.
+    pub fn to_triangles(&self) -> Vec<ObjectShape> {
+        self.triangulate()
+            .iter()
+            .map(|corners| {
+                let [p1, p2, p3] = corners.map(|corner| corner.vertex.to_point());
+                if corners
+                    .iter()
+                    .all(|corner| corner.texture_coordinates.is_none() && corner.normal.is_none())
+                {
+                    return ObjectShape::Triangle(p1, p2, p3);
+                }
+                let face_normal = Normal::new(
+                    (p2.clone() - p1.clone())
+                        .cross(&(p3.clone() - p1.clone()))
+                        .to_array(),
+                )
+                .normalized();
+                let default_texture_coordinates = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+                let [m1, m2, m3] = [0, 1, 2].map(|i| {
+                    let corner = corners[i];
+                    MappedTrianglePoint::new(
+                        [&p1, &p2, &p3][i].clone(),
+                        corner
+                            .texture_coordinates
+                            .map_or(default_texture_coordinates[i], |c| c.to_array()),
+                        corner
+                            .normal
+                            .and_then(Vector::to_normal)
+                            .unwrap_or_else(|| face_normal.clone()),
+                    )
+                });
+                ObjectShape::MappedTriangle(MappedTriangle::new(m1, m2, m3))
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_to_polygon() {
+    let (_, vertex) = vertex::parse_vertex("v 0 0 0").unwrap();
+    let (_, normal) = vector::parse_vector("vn 0 0 1").unwrap();
+    let (_, indices) = parse_polygon_indices("f 1//1 1//1 1//1").unwrap();
+    assert_eq!(
+        indices.to_polygon(&[vertex], &[], &[normal]),
+        Ok(Polygon {
+            points: vec![
+                PolygonVertex {
+                    vertex,
+                    texture_coordinates: None,
+                    normal: Some(normal),
+                };
+                3
+            ],
+            triangles: vec![[0, 1, 2]],
+        })
+    );
+    let (_, indices) = parse_polygon_indices("f 1 1 2").unwrap();
+    assert!(indices.to_polygon(&[vertex], &[], &[]).is_err());
+    let (_, indices) = parse_polygon_indices("f 1/1 1/1 1/1").unwrap();
+    assert!(indices.to_polygon(&[vertex], &[], &[]).is_err());
+    let (_, indices) = parse_polygon_indices("f 1 1").unwrap();
+    assert!(indices.to_polygon(&[vertex], &[], &[]).is_err());
+}
+
+#[test]
+fn test_to_triangles_interpolates_normals() {
+    use crate::ray::Ray;
+    let vertices: Vec<Vertex> = ["v 0 0 0", "v 1 0 0", "v 0 1 0"]
+        .iter()
+        .map(|line| vertex::parse_vertex(line).unwrap().1)
+        .collect();
+    let normals: Vec<Vector> = ["vn 0 0 1", "vn 1 0 1", "vn 0 1 1"]
+        .iter()
+        .map(|line| vector::parse_vector(line).unwrap().1)
+        .collect();
+    let (_, indices) = parse_polygon_indices("f 1//1 2//2 3//3").unwrap();
+    let triangles = indices
+        .to_polygon(&vertices, &[], &normals)
+        .unwrap()
+        .to_triangles();
+    assert!(matches!(triangles[0], ObjectShape::MappedTriangle(_)));
+
+    let ray = Ray {
+        from: crate::vector::Point::new([0.25, 0.25, 1.0]),
+        direction: crate::vector::Direction::new([0.0, 0.0, -1.0]),
+    };
+    let hit = triangles[0].intersection(&ray).unwrap();
+    assert_eq!(hit.texture_coordinates, [0.25, 0.25]);
+    // weights of 1/2, 1/4 and 1/4 on the unit corner normals
+    let side = 0.25 / 2f64.sqrt();
+    let expected = crate::vector::Direction::new([side, side, 0.5 + 2.0 * side]).normalized();
+    assert!((hit.normal.dot(&expected) - 1.0).abs() < 1e-12);
+
+    // a zero vn falls back to the face normal instead of spreading NaN
+    let (_, zero) = vector::parse_vector("vn 0 0 0").unwrap();
+    let (_, indices) = parse_polygon_indices("f 1//1 2//1 3//2").unwrap();
+    let triangles = indices
+        .to_polygon(&vertices, &[], &[zero, normals[0]])
+        .unwrap()
+        .to_triangles();
+    let hit = triangles[0].intersection(&ray).unwrap();
+    assert_eq!(hit.normal.to_array(), [0.0, 0.0, 1.0]);
+
+    // without any vn or vt data, triangles stay flat
+    let (_, indices) = parse_polygon_indices("f 1 2 3").unwrap();
+    let triangles = indices
+        .to_polygon(&vertices, &[], &[])
+        .unwrap()
+        .to_triangles();
+    assert!(matches!(triangles[0], ObjectShape::Triangle(..)));
 }
-*/
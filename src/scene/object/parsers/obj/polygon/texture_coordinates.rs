@@ -8,7 +8,13 @@ pub struct TextureCoordinates {
     w: f64,
 }
 
-fn parse_texture_coordinates(input: &str) -> IResult<&str, TextureCoordinates> {
+impl TextureCoordinates {
+    pub fn to_array(self) -> [f64; 2] {
+        [self.u, self.v]
+    }
+}
+
+pub fn parse_texture_coordinates(input: &str) -> IResult<&str, TextureCoordinates> {
     let (input, _) = tws(tag("vt"))(input)?;
     let (input, (u, v, w)) = tuple((
         tws(parse_float),
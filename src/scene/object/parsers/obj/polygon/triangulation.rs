@@ -0,0 +1,234 @@
+use std::f64::consts::PI;
+
+/// Split a simple polygon, given by its corners in order, into triangles of corner indices.
+/// Convex polygons are fanned out from the first corner, and concave ones are split by ear
+/// clipping. The polygon is flattened onto the plane of its Newell normal first, so corners
+/// which are slightly out of plane are tolerated. Fails when the polygon crosses itself, either
+/// winding round more than once, like a star, or so badly that no ear is left to clip.
+pub fn triangulate(points: &[[f64; 3]]) -> Result<Vec<[usize; 3]>, &'static str> {
+    let flat = project(points);
+    match left_turning(&flat) {
+        // turning left all the way round, once
+        Some(turning) if turning < 3.0 * PI => {
+            Ok((1..points.len() - 1).map(|i| [0, i, i + 1]).collect())
+        }
+        Some(_) => Err("polygon intersects itself"),
+        None => clip_ears(&flat),
+    }
+}
+
+/// Newell's method: a normal which is robust to non-planar and concave polygons, and whose
+/// length is twice the area of the polygon
+fn newell_normal(points: &[[f64; 3]]) -> [f64; 3] {
+    let mut normal = [0.0; 3];
+    for (i, current) in points.iter().enumerate() {
+        let next = points[(i + 1) % points.len()];
+        normal[0] += (current[1] - next[1]) * (current[2] + next[2]);
+        normal[1] += (current[2] - next[2]) * (current[0] + next[0]);
+        normal[2] += (current[0] - next[0]) * (current[1] + next[1]);
+    }
+    normal
+}
+
+/// Drop the axis the polygon faces most along, keeping the remaining two in the order that
+/// makes the polygon counter-clockwise
+fn project(points: &[[f64; 3]]) -> Vec<[f64; 2]> {
+    let normal = newell_normal(points);
+    let axis = (0..3)
+        .max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))
+        .unwrap(); // range is not empty
+    let (mut u, mut v) = ((axis + 1) % 3, (axis + 2) % 3);
+    if normal[axis] < 0.0 {
+        std::mem::swap(&mut u, &mut v);
+    }
+    points.iter().map(|point| [point[u], point[v]]).collect()
+}
+
+/// Twice the signed area of triangle abc: positive when counter-clockwise
+fn cross(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// The total angle turned through going round the polygon, which is a multiple of 2 pi, if
+/// no corner turns right. Turning back on itself counts as a left turn of pi.
+fn left_turning(points: &[[f64; 2]]) -> Option<f64> {
+    let n = points.len();
+    let mut turning = 0.0;
+    for i in 0..n {
+        let [a, b, c] = [0, 1, 2].map(|offset| points[(i + offset) % n]);
+        let turn = cross(a, b, c);
+        if turn < 0.0 {
+            return None;
+        }
+        let dot = (b[0] - a[0]) * (c[0] - b[0]) + (b[1] - a[1]) * (c[1] - b[1]);
+        turning += turn.atan2(dot);
+    }
+    Some(turning)
+}
+
+fn contains(triangle: [[f64; 2]; 3], point: [f64; 2]) -> bool {
+    let [a, b, c] = triangle;
+    cross(a, b, point) >= 0.0 && cross(b, c, point) >= 0.0 && cross(c, a, point) >= 0.0
+}
+
+fn clip_ears(points: &[[f64; 2]]) -> Result<Vec<[usize; 3]>, &'static str> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| {
+            [
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            ]
+        };
+        let turn = |i: usize| {
+            let [a, b, c] = corner(i).map(|index| points[index]);
+            cross(a, b, c)
+        };
+        // only a reflex corner can poke into an ear; collinear corners lying on its edges
+        // must not block it
+        let reflex: Vec<usize> = (0..n).filter(|&i| turn(i) < 0.0).collect();
+        let is_ear = |i: usize| {
+            if turn(i) <= 0.0 {
+                return false; // reflex or degenerate corner
+            }
+            let corners = corner(i);
+            let triangle = corners.map(|index| points[index]);
+            !reflex
+                .iter()
+                .map(|&j| remaining[j])
+                .filter(|index| !corners.contains(index))
+                .any(|index| contains(triangle, points[index]))
+        };
+        if let Some(ear) = (0..n).find(|&i| is_ear(i)) {
+            triangles.push(corner(ear));
+            remaining.remove(ear);
+            continue;
+        }
+        // a simple polygon always has an ear once its collinear corners are gone, and
+        // dropping those loses no area
+        let degenerate = (0..n)
+            .find(|&i| turn(i) == 0.0)
+            .ok_or("polygon intersects itself")?;
+        remaining.remove(degenerate);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    Ok(triangles)
+}
+
+#[cfg(test)]
+fn area(points: &[[f64; 3]], triangles: &[[usize; 3]]) -> f64 {
+    let flat = project(points);
+    triangles
+        .iter()
+        .map(|&[a, b, c]| cross(flat[a], flat[b], flat[c]) / 2.0)
+        .sum()
+}
+
+#[test]
+fn test_triangulate_convex() {
+    let square = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    assert_eq!(triangulate(&square), Ok(vec![[0, 1, 2], [0, 2, 3]]));
+    // facing the other way, and slightly bent out of plane
+    let square = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [1.0, 0.05, 1.0],
+        [1.0, 0.0, 0.0],
+    ];
+    assert_eq!(triangulate(&square).unwrap().len(), 2);
+}
+
+#[test]
+fn test_triangulate_concave() {
+    // an L shape, in the y-z plane, whose reflex corner is the fourth
+    let l_shape = [
+        [0.0, 0.0, 0.0],
+        [0.0, 2.0, 0.0],
+        [0.0, 2.0, 1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, 1.0, 2.0],
+        [0.0, 0.0, 2.0],
+    ];
+    let triangles = triangulate(&l_shape).unwrap();
+    assert_eq!(triangles.len(), 4);
+    assert!((area(&l_shape, &triangles) - 3.0).abs() < 1e-12);
+    // no triangle may cover the notch
+    let flat = project(&l_shape);
+    for &[a, b, c] in triangles.iter() {
+        assert!(cross(flat[a], flat[b], flat[c]) > 0.0);
+        assert!(!contains([flat[a], flat[b], flat[c]], [1.5, 1.5]));
+    }
+}
+
+#[test]
+fn test_triangulate_collinear() {
+    // the same L shape with an extra corner halfway along each of its long sides
+    let l_shape = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [2.0, 0.0, 0.0],
+        [2.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 2.0, 0.0],
+        [0.0, 2.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    let triangles = triangulate(&l_shape).unwrap();
+    assert!((area(&l_shape, &triangles) - 3.0).abs() < 1e-12);
+    let flat = project(&l_shape);
+    for &[a, b, c] in triangles.iter() {
+        assert!(cross(flat[a], flat[b], flat[c]) > 0.0);
+        assert!(!contains([flat[a], flat[b], flat[c]], [1.5, 1.5]));
+    }
+    // a square with a spike of no width doubling back along x = 1: no corner is an ear until
+    // the spike's collinear corners are dropped, and none of the triangles may be flipped
+    let spiked = [
+        [0.0, 0.0, 0.0],
+        [2.0, 0.0, 0.0],
+        [2.0, 2.0, 0.0],
+        [1.0, 2.0, 0.0],
+        [1.0, 3.0, 0.0],
+        [1.0, 2.0, 0.0],
+        [0.0, 2.0, 0.0],
+    ];
+    let triangles = triangulate(&spiked).unwrap();
+    assert!((area(&spiked, &triangles) - 4.0).abs() < 1e-12);
+    let flat = project(&spiked);
+    for &[a, b, c] in triangles.iter() {
+        assert!(cross(flat[a], flat[b], flat[c]) >= 0.0);
+    }
+}
+
+#[test]
+fn test_triangulate_self_intersecting() {
+    let tangle = [
+        [0.0, 2.0, 0.0],
+        [0.0, 0.0, 0.0],
+        [1.0, 2.0, 0.0],
+        [2.0, 2.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ];
+    assert!(triangulate(&tangle).is_err());
+}
+
+#[test]
+fn test_triangulate_pentagram() {
+    // every corner turns left, but the outline winds round twice
+    let pentagram: Vec<[f64; 3]> = (0..5)
+        .map(|i| {
+            let angle = (2 * i) as f64 * 2.0 * PI / 5.0;
+            [angle.cos(), angle.sin(), 0.0]
+        })
+        .collect();
+    assert!(left_turning(&project(&pentagram)).is_some());
+    assert!(triangulate(&pentagram).is_err());
+}
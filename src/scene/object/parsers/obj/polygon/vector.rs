@@ -1,4 +1,7 @@
-use crate::scene::object::parsers::common::{float::parse_float, whitespace::tws};
+use crate::{
+    scene::object::parsers::common::{float::parse_float, whitespace::tws},
+    vector::Normal,
+};
 use nom::{bytes::complete::tag, sequence::tuple, IResult};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -8,7 +11,17 @@ pub struct Vector {
     z: f64,
 }
 
-fn parse_vector(input: &str) -> IResult<&str, Vector> {
+impl Vector {
+    /// None for a zero vector, which has no direction to normalize
+    pub fn to_normal(self) -> Option<Normal> {
+        if self.x == 0.0 && self.y == 0.0 && self.z == 0.0 {
+            return None;
+        }
+        Some(Normal::new([self.x, self.y, self.z]).normalized())
+    }
+}
+
+pub fn parse_vector(input: &str) -> IResult<&str, Vector> {
     let (input, _) = tws(tag("vn"))(input)?;
     let (input, (x, y, z)) = tuple((tws(parse_float), tws(parse_float), tws(parse_float)))(input)?;
     Ok((input, Vector { x, y, z }))
@@ -39,4 +52,6 @@ fn test_parse_vector() {
             }
         ))
     );
+    let (_, zero) = parse_vector("vn 0 0 0").unwrap();
+    assert!(zero.to_normal().is_none());
 }
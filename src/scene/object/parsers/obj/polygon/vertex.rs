@@ -1,4 +1,7 @@
-use crate::scene::object::parsers::common::{float::parse_float, whitespace::tws};
+use crate::{
+    scene::object::parsers::common::{float::parse_float, whitespace::tws},
+    vector::Point,
+};
 use nom::{bytes::complete::tag, combinator::opt, sequence::tuple, IResult};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -9,7 +12,13 @@ pub struct Vertex {
     w: f64,
 }
 
-fn parse_vertex(input: &str) -> IResult<&str, Vertex> {
+impl Vertex {
+    pub fn to_point(self) -> Point {
+        Point::new([self.x / self.w, self.y / self.w, self.z / self.w])
+    }
+}
+
+pub fn parse_vertex(input: &str) -> IResult<&str, Vertex> {
     let (input, _) = tws(tag("v"))(input)?;
     let (input, (x, y, z, w)) = tuple((
         tws(parse_float),
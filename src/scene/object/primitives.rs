@@ -0,0 +1,543 @@
+//! Analytic shapes in their own object space, where each is centred on the origin and, if it
+//! has an axis, is symmetric about y. All but the plane fit within [-1, 1] on every axis.
+//! Objects place them in the world with their `AffineMatrix`.
+use crate::{ray::Ray, vector::Normal};
+use std::f64::consts::PI;
+
+pub mod polynomial;
+
+/// A point where a ray passes through the surface of a shape
+pub struct Crossing {
+    /// Along the ray, which may be behind its start
+    pub distance: f64,
+    /// Pointing out of the shape, not necessarily of unit length
+    pub normal: Normal,
+    pub texture_coordinates: [f64; 2],
+}
+
+/// Around the y axis, matching the longitude of the sphere's texture coordinates
+fn azimuth(x: f64, z: f64) -> f64 {
+    0.5 + x.atan2(z) / (2.0 * PI)
+}
+
+fn point_at(ray: &Ray, distance: f64) -> [f64; 3] {
+    let [x, y, z] = ray.from.to_array();
+    let [dx, dy, dz] = ray.direction.to_array();
+    [x + distance * dx, y + distance * dy, z + distance * dz]
+}
+
+/// Crossing of the plane y = `height`, facing up or down, if it is within the unit circle
+fn cap(ray: &Ray, height: f64, up: bool) -> Option<Crossing> {
+    let (from, direction) = (ray.from.to_array()[1], ray.direction.to_array()[1]);
+    if direction == 0.0 {
+        return None;
+    }
+    let distance = (height - from) / direction;
+    let [x, _, z] = point_at(ray, distance);
+    if x * x + z * z > 1.0 {
+        return None;
+    }
+    let (y, v) = if up {
+        (1.0, (1.0 - z) / 2.0)
+    } else {
+        (-1.0, (1.0 + z) / 2.0)
+    };
+    Some(Crossing {
+        distance,
+        normal: Normal::new([0.0, y, 0.0]),
+        texture_coordinates: [(x + 1.0) / 2.0, v],
+    })
+}
+
+fn sorted(mut crossings: Vec<Crossing>) -> Vec<Crossing> {
+    crossings.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    crossings
+}
+
+/// The sphere of radius 1, mapped by longitude around the y axis and latitude
+pub fn sphere(ray: &Ray) -> Vec<Crossing> {
+    // |F + tD|^2 = 1
+    let from = ray.from.from_origin();
+    let a = ray.direction.dot(&ray.direction);
+    let b = 2.0 * ray.direction.dot(&from);
+    let c = from.dot(&from) - 1.0;
+    polynomial::quadratic(a, b, c)
+        .into_iter()
+        .map(|distance| {
+            let [x, y, z] = point_at(ray, distance);
+            Crossing {
+                distance,
+                normal: Normal::new([x, y, z]),
+                texture_coordinates: [azimuth(x, z), 0.5 + y.clamp(-1.0, 1.0).asin() / PI],
+            }
+        })
+        .collect()
+}
+
+/// The plane y = 0, facing up, with texture coordinates (x, -z) so that images repeat every
+/// unit
+pub fn plane(ray: &Ray) -> Vec<Crossing> {
+    let (from, direction) = (ray.from.to_array()[1], ray.direction.to_array()[1]);
+    if direction == 0.0 {
+        return vec![];
+    }
+    let distance = -from / direction;
+    let [x, _, z] = point_at(ray, distance);
+    vec![Crossing {
+        distance,
+        normal: Normal::new([0.0, 1.0, 0.0]),
+        texture_coordinates: [x, -z],
+    }]
+}
+
+/// The unit disk in the plane y = 0, facing up, with an image stretched across its square
+pub fn disk(ray: &Ray) -> Vec<Crossing> {
+    cap(ray, 0.0, true).into_iter().collect()
+}
+
+/// The cube from -1 to 1, with an image on each face, upright when seen from outside with y
+/// up, or with -z up on the top and bottom faces
+pub fn cuboid(ray: &Ray) -> Vec<Crossing> {
+    let from = ray.from.to_array();
+    let direction = ray.direction.to_array();
+    // the range of distances inside each pair of faces, and the axis they leave the ray by
+    let mut entry = (f64::NEG_INFINITY, 0);
+    let mut exit = (f64::INFINITY, 0);
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if from[axis].abs() > 1.0 {
+                return vec![];
+            }
+            continue;
+        }
+        let near = (-direction[axis].signum() - from[axis]) / direction[axis];
+        let far = (direction[axis].signum() - from[axis]) / direction[axis];
+        if near > entry.0 {
+            entry = (near, axis);
+        }
+        if far < exit.0 {
+            exit = (far, axis);
+        }
+    }
+    if entry.0 > exit.0 || entry.0.is_infinite() {
+        return vec![];
+    }
+    let crossing = |(distance, axis): (f64, usize), outwards: f64| {
+        let mut normal = [0.0; 3];
+        normal[axis] = outwards * direction[axis].signum();
+        let [x, y, z] = point_at(ray, distance);
+        let texture_coordinates = match (axis, normal[axis] > 0.0) {
+            (0, true) => [1.0 - z, y + 1.0],
+            (0, false) => [z + 1.0, y + 1.0],
+            (1, true) => [x + 1.0, 1.0 - z],
+            (1, false) => [x + 1.0, z + 1.0],
+            (_, true) => [x + 1.0, y + 1.0],
+            (_, false) => [1.0 - x, y + 1.0],
+        };
+        Crossing {
+            distance,
+            normal: Normal::new(normal),
+            texture_coordinates: texture_coordinates.map(|c| c / 2.0),
+        }
+    };
+    vec![crossing(entry, -1.0), crossing(exit, 1.0)]
+}
+
+/// The tube of radius 1 around the y axis, from y = -1 to 1, and its ends if `capped`. The
+/// side is mapped like a sphere, and the ends like disks.
+pub fn cylinder(ray: &Ray, capped: bool) -> Vec<Crossing> {
+    let [x, _, z] = ray.from.to_array();
+    let [dx, _, dz] = ray.direction.to_array();
+    // x^2 + z^2 = 1
+    let a = dx * dx + dz * dz;
+    let b = 2.0 * (x * dx + z * dz);
+    let c = x * x + z * z - 1.0;
+    let mut crossings: Vec<Crossing> = polynomial::quadratic(a, b, c)
+        .into_iter()
+        .filter_map(|distance| {
+            let [x, y, z] = point_at(ray, distance);
+            (-1.0..=1.0).contains(&y).then(|| Crossing {
+                distance,
+                normal: Normal::new([x, 0.0, z]),
+                texture_coordinates: [azimuth(x, z), (y + 1.0) / 2.0],
+            })
+        })
+        .collect();
+    if capped {
+        crossings.extend(cap(ray, 1.0, true));
+        crossings.extend(cap(ray, -1.0, false));
+    }
+    sorted(crossings)
+}
+
+/// The cone with its apex at y = 1 and a base of radius 1 at y = -1, closed by the base if
+/// `capped`. The side is mapped like a sphere, and the base like a disk.
+pub fn cone(ray: &Ray, capped: bool) -> Vec<Crossing> {
+    let [x, y, z] = ray.from.to_array();
+    let [dx, dy, dz] = ray.direction.to_array();
+    // x^2 + z^2 = ((1 - y) / 2)^2
+    let w = 1.0 - y;
+    let a = dx * dx + dz * dz - dy * dy / 4.0;
+    let b = 2.0 * (x * dx + z * dz) + w * dy / 2.0;
+    let c = x * x + z * z - w * w / 4.0;
+    let mut crossings: Vec<Crossing> = polynomial::quadratic(a, b, c)
+        .into_iter()
+        .filter_map(|distance| {
+            let [x, y, z] = point_at(ray, distance);
+            (-1.0..=1.0).contains(&y).then(|| Crossing {
+                distance,
+                // the gradient of x^2 + z^2 - ((1 - y) / 2)^2, halved
+                normal: Normal::new([x, (1.0 - y) / 4.0, z]),
+                texture_coordinates: [azimuth(x, z), (y + 1.0) / 2.0],
+            })
+        })
+        .collect();
+    if capped {
+        crossings.extend(cap(ray, -1.0, false));
+    }
+    sorted(crossings)
+}
+
+/// The ring around the y axis whose tube has its centre 1 from the axis and the given radius.
+/// Texture coordinates go around the axis like a sphere's, then around the tube from its
+/// inside edge, where the seam is least visible.
+pub fn torus(ray: &Ray, minor_radius: f64) -> Vec<Crossing> {
+    // solve from the closest point to the centre, so that the coefficients stay small however
+    // far away the ray starts
+    let start = -ray.from.from_origin().dot(&ray.direction);
+    let [x, y, z] = point_at(ray, start);
+    let [dx, dy, dz] = ray.direction.to_array();
+    // (x^2 + y^2 + z^2 + 1 - r^2)^2 = 4 (x^2 + z^2)
+    let m = x * dx + y * dy + z * dz;
+    let k = x * x + y * y + z * z + 1.0 - minor_radius * minor_radius;
+    let coefficients = [
+        1.0,
+        4.0 * m,
+        4.0 * m * m + 2.0 * k - 4.0 * (dx * dx + dz * dz),
+        4.0 * m * k - 8.0 * (x * dx + z * dz),
+        k * k - 4.0 * (x * x + z * z),
+    ];
+    let [a, b, c, d, e] = coefficients;
+    // a ray grazing the tube touches it without passing through, which would upset the
+    // alternation of entering and leaving that combining solids relies on
+    polynomial::quartic_sign_changes(a, b, c, d, e)
+        .into_iter()
+        .map(|distance| {
+            let distance = start + distance;
+            let [x, y, z] = point_at(ray, distance);
+            // from the centre of the tube
+            let radial = x.hypot(z);
+            let [tube_x, tube_z] = [x - x / radial, z - z / radial];
+            let around_tube = y.atan2(radial - 1.0);
+            Crossing {
+                distance,
+                normal: Normal::new([tube_x, y, tube_z]),
+                texture_coordinates: [azimuth(x, z), 0.5 + around_tube / (2.0 * PI)],
+            }
+        })
+        .collect()
+}
+
+/// The surface where a quadratic function of position is 0, such as an ellipsoid, paraboloid
+/// or hyperboloid, clipped to the cube from -1 to 1 and mapped like a cylinder
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quadric {
+    /// Symmetric, so that the function is p^T Q p for the point p = (x, y, z, 1)
+    matrix: [[f64; 4]; 4],
+}
+
+impl Quadric {
+    /// The surface a x^2 + b y^2 + c z^2 + d xy + e xz + f yz + g x + h y + i z + j = 0, given
+    /// the coefficients a to j, with its inside where the function is negative
+    pub fn new([a, b, c, d, e, f, g, h, i, j]: [f64; 10]) -> Quadric {
+        Quadric {
+            matrix: [
+                [a, d / 2.0, e / 2.0, g / 2.0],
+                [d / 2.0, b, f / 2.0, h / 2.0],
+                [e / 2.0, f / 2.0, c, i / 2.0],
+                [g / 2.0, h / 2.0, i / 2.0, j],
+            ],
+        }
+    }
+
+    /// Centred on the origin, with the given radii along each axis
+    pub fn ellipsoid([a, b, c]: [f64; 3]) -> Quadric {
+        let square = |radius: f64| 1.0 / (radius * radius);
+        Quadric::new([
+            square(a),
+            square(b),
+            square(c),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+        ])
+    }
+
+    /// Opening upwards around the y axis, from its tip at y = -1 to radius 1 at y = 1
+    pub fn paraboloid() -> Quadric {
+        Quadric::new([1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -0.5, 0.0, -0.5])
+    }
+
+    /// Of one sheet around the y axis, with the given radius at y = 0 and radius 1 at y = -1
+    /// and 1
+    pub fn hyperboloid(waist_radius: f64) -> Quadric {
+        let waist = waist_radius * waist_radius;
+        Quadric::new([1.0, waist - 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -waist])
+    }
+
+    /// Of two sheets around the y axis, with their tips at y = -`vertex` and `vertex`, and
+    /// radius 1 at y = -1 and 1
+    pub fn hyperboloid_of_two_sheets(vertex: f64) -> Quadric {
+        let flare = 1.0 / (1.0 - vertex * vertex);
+        Quadric::new([
+            1.0,
+            -flare,
+            1.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            vertex * vertex * flare,
+        ])
+    }
+
+    /// Q v, for a point, with w = 1, or a direction, with w = 0
+    fn apply(&self, [x, y, z]: [f64; 3], w: f64) -> [f64; 4] {
+        self.matrix
+            .map(|row| row[0] * x + row[1] * y + row[2] * z + row[3] * w)
+    }
+
+    pub fn crossings(&self, ray: &Ray) -> Vec<Crossing> {
+        let from = ray.from.to_array();
+        let direction = ray.direction.to_array();
+        let dot =
+            |a: [f64; 3], w: f64, b: [f64; 4]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + w * b[3];
+        let q_direction = self.apply(direction, 0.0);
+        // (F + tD)^T Q (F + tD) = 0
+        let a = dot(direction, 0.0, q_direction);
+        let b = 2.0 * dot(from, 1.0, q_direction);
+        let c = dot(from, 1.0, self.apply(from, 1.0));
+        polynomial::quadratic(a, b, c)
+            .into_iter()
+            .filter_map(|distance| {
+                let point = point_at(ray, distance);
+                if point.iter().any(|c| c.abs() > 1.0) {
+                    return None;
+                }
+                // half the gradient
+                let [nx, ny, nz, _] = self.apply(point, 1.0);
+                let [x, y, z] = point;
+                Some(Crossing {
+                    distance,
+                    normal: Normal::new([nx, ny, nz]),
+                    texture_coordinates: [azimuth(x, z), (y + 1.0) / 2.0],
+                })
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_primitives() {
+    use crate::vector::{Direction, Point};
+    let ray = |from: [f64; 3], direction: [f64; 3]| Ray {
+        from: Point::new(from),
+        direction: Direction::new(direction).normalized(),
+    };
+    let summary = |crossings: Vec<Crossing>| -> Vec<(f64, [f64; 3])> {
+        crossings
+            .iter()
+            .map(|crossing| (crossing.distance, crossing.normal.normalized().to_array()))
+            .collect()
+    };
+    let down = ray([0.5, 2.0, 0.5], [0.0, -1.0, 0.0]);
+    let plane_crossings = plane(&down);
+    assert_eq!(plane_crossings[0].distance, 2.0);
+    assert_eq!(plane_crossings[0].texture_coordinates, [0.5, -0.5]);
+    assert_eq!(summary(disk(&down)), vec![(2.0, [0.0, 1.0, 0.0])]);
+    assert!(disk(&ray([1.0, 2.0, 0.5], [0.0, -1.0, 0.0])).is_empty());
+    assert!(plane(&ray([0.0, 2.0, 0.0], [1.0, 0.0, 0.0])).is_empty());
+
+    let along_x = ray([-3.0, 0.5, 0.0], [1.0, 0.0, 0.0]);
+    assert_eq!(
+        summary(cuboid(&along_x)),
+        vec![(2.0, [-1.0, 0.0, 0.0]), (4.0, [1.0, 0.0, 0.0])]
+    );
+    assert_eq!(cuboid(&along_x)[0].texture_coordinates, [0.5, 0.75]);
+    assert!(cuboid(&ray([-3.0, 1.5, 0.0], [1.0, 0.0, 0.0])).is_empty());
+    // through an edge and out of the top
+    let diagonal = cuboid(&ray([-2.0, 0.0, 0.0], [1.0, 1.0, 0.0]));
+    assert_eq!(diagonal.len(), 2);
+    assert_eq!(diagonal[1].normal.to_array(), [0.0, 1.0, 0.0]);
+
+    assert_eq!(
+        summary(cylinder(&along_x, false)),
+        vec![(2.0, [-1.0, 0.0, 0.0]), (4.0, [1.0, 0.0, 0.0])]
+    );
+    let texture_coordinates = cylinder(&along_x, false)[0].texture_coordinates;
+    assert!((texture_coordinates[0] - 0.25).abs() < 1e-12);
+    assert_eq!(texture_coordinates[1], 0.75);
+    // down the middle of the tube, only meeting the caps
+    assert!(cylinder(&down, false).is_empty());
+    let capped = summary(cylinder(&ray([0.0, 2.0, 0.0], [0.0, -1.0, 0.0]), true));
+    assert_eq!(
+        capped,
+        vec![(1.0, [0.0, 1.0, 0.0]), (3.0, [0.0, -1.0, 0.0])]
+    );
+
+    // in and out through the side where the radius is 1/2
+    let crossings = cone(&ray([-2.0, 0.0, 0.0], [1.0, 0.0, 0.0]), false);
+    assert_eq!(crossings.len(), 2);
+    assert_eq!(crossings[0].distance, 1.5);
+    let normal = crossings[0].normal.normalized().to_array();
+    let expected = [-2.0 / 5f64.sqrt(), 1.0 / 5f64.sqrt(), 0.0];
+    assert!((0..3).all(|i| (normal[i] - expected[i]).abs() < 1e-12));
+    let crossings = summary(cone(&ray([0.25, 3.0, 0.0], [0.0, -1.0, 0.0]), true));
+    assert_eq!(crossings.len(), 2);
+    assert!((crossings[0].0 - 2.5).abs() < 1e-12);
+    assert_eq!(crossings[1], (4.0, [0.0, -1.0, 0.0]));
+
+    // placed in the world by an object's transformation
+    use crate::scene::object::{
+        matrix::{rotation::Orientation, AffineTransformation},
+        Intersectable, Object, ObjectShape,
+    };
+    let cylinder = Object::new(
+        ObjectShape::Cylinder { capped: true },
+        Some(AffineTransformation {
+            scale: [2.0, 0.5, 2.0],
+            position: [0.0, 1.0, -5.0],
+            orientation: Orientation::IDENTITY,
+        }),
+        None,
+    );
+    let hit = cylinder
+        .intersect(&ray([0.0, 1.0, 0.0], [0.0, 0.0, -1.0]))
+        .unwrap();
+    assert_eq!(hit.point.to_array(), [0.0, 1.0, -3.0]);
+    assert_eq!(hit.normal.to_array(), [0.0, 0.0, 1.0]);
+    let hit = cylinder
+        .intersect(&ray([0.0, 3.0, -5.0], [0.0, -1.0, 0.0]))
+        .unwrap();
+    assert_eq!(hit.point.to_array(), [0.0, 1.5, -5.0]);
+    assert_eq!(hit.normal.to_array(), [0.0, 1.0, 0.0]);
+
+    // a sphere stretched into an ellipsoid, lit by the normals of the analytic surface
+    let (centre, radii) = ([1.0, 2.0, -10.0], [1.0, 3.0, 1.0]);
+    let ellipsoid = Object::new(
+        ObjectShape::Sphere,
+        Some(AffineTransformation {
+            scale: radii,
+            position: centre,
+            orientation: Orientation::IDENTITY,
+        }),
+        None,
+    );
+    let analytic = |point: [f64; 3]| {
+        let normal = [0, 1, 2].map(|i| (point[i] - centre[i]) / (radii[i] * radii[i]));
+        Normal::new(normal).normalized().to_array()
+    };
+    let close = |a: [f64; 3], b: [f64; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-9);
+    for i in 0..10 {
+        for j in 0..10 {
+            // aimed across its outline, seen from the origin
+            let (u, v) = (i as f64 / 5.0 - 0.9, j as f64 / 5.0 - 0.9);
+            if u * u + v * v > 0.9 {
+                continue;
+            }
+            let target = [
+                centre[0] + u * radii[0],
+                centre[1] + v * radii[1],
+                centre[2],
+            ];
+            let hit = ellipsoid.intersect(&ray([0.0; 3], target)).unwrap();
+            assert!(close(hit.normal.to_array(), analytic(hit.point.to_array())));
+        }
+    }
+}
+
+#[test]
+fn test_torus_and_quadrics() {
+    use crate::vector::{Direction, Point};
+    let ray = |from: [f64; 3], direction: [f64; 3]| Ray {
+        from: Point::new(from),
+        direction: Direction::new(direction).normalized(),
+    };
+    let close = |a: [f64; 3], b: [f64; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-9);
+
+    // through both sides of the ring, from far away
+    let crossings = torus(&ray([-100.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 0.25);
+    let distances: Vec<f64> = crossings.iter().map(|crossing| crossing.distance).collect();
+    let expected = [98.75, 99.25, 100.75, 101.25];
+    assert!((0..4).all(|i| (distances[i] - expected[i]).abs() < 1e-9));
+    let normals: Vec<[f64; 3]> = crossings
+        .iter()
+        .map(|crossing| crossing.normal.normalized().to_array())
+        .collect();
+    assert!(close(normals[0], [-1.0, 0.0, 0.0]));
+    assert!(close(normals[1], [1.0, 0.0, 0.0]));
+    // on the outside edge, half way around the tube from the inside edge
+    assert!((crossings[0].texture_coordinates[1] - 0.5).abs() < 1e-9);
+    // through the hole
+    assert!(torus(&ray([0.0, 5.0, 0.0], [0.0, -1.0, 0.0]), 0.25).is_empty());
+    // grazing the top of the tube on both sides, without passing through it
+    assert!(torus(&ray([-5.0, 0.25, 0.0], [1.0, 0.0, 0.0]), 0.25).is_empty());
+    // onto the top of the tube, with its normal straight up
+    let crossings = torus(&ray([0.0, 5.0, 1.0], [0.0, -1.0, 0.0]), 0.25);
+    assert!((crossings[0].distance - 4.75).abs() < 1e-9);
+    assert!(close(
+        crossings[0].normal.normalized().to_array(),
+        [0.0, 1.0, 0.0]
+    ));
+
+    // the ellipsoid's normals are those of the analytic surface
+    let radii = [1.0, 0.5, 0.25];
+    let ellipsoid = Quadric::ellipsoid(radii);
+    let direction = [-2.0, -0.9, -0.6];
+    let crossings = ellipsoid.crossings(&ray([2.0, 1.0, 0.5], direction));
+    assert_eq!(crossings.len(), 2);
+    for crossing in crossings.iter() {
+        let [x, y, z] = point_at(&ray([2.0, 1.0, 0.5], direction), crossing.distance);
+        let on_surface = (x / radii[0]).powi(2) + (y / radii[1]).powi(2) + (z / radii[2]).powi(2);
+        assert!((on_surface - 1.0).abs() < 1e-9);
+        let analytic = Normal::new([
+            x / (radii[0] * radii[0]),
+            y / (radii[1] * radii[1]),
+            z / (radii[2] * radii[2]),
+        ]);
+        assert!(close(
+            crossing.normal.normalized().to_array(),
+            analytic.normalized().to_array()
+        ));
+    }
+
+    let across = ray([-3.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+    // a paraboloid has radius 1/sqrt(2) half way up
+    let crossings = Quadric::paraboloid().crossings(&across);
+    assert!((crossings[0].distance - (3.0 - 0.5f64.sqrt())).abs() < 1e-9);
+    let crossings = Quadric::hyperboloid(0.5).crossings(&across);
+    assert_eq!(crossings.len(), 2);
+    assert!((crossings[0].distance - 2.5).abs() < 1e-9);
+    assert!(close(
+        crossings[0].normal.normalized().to_array(),
+        [-1.0, 0.0, 0.0]
+    ));
+    // across the gap between the sheets, then up the axis through both tips
+    assert!(Quadric::hyperboloid_of_two_sheets(0.5)
+        .crossings(&across)
+        .is_empty());
+    let crossings =
+        Quadric::hyperboloid_of_two_sheets(0.5).crossings(&ray([0.0, 0.0, 0.0], [0.0, 1.0, 0.0]));
+    let distances: Vec<f64> = crossings.iter().map(|crossing| crossing.distance).collect();
+    assert!((distances[0] + 0.5).abs() < 1e-9 && (distances[1] - 0.5).abs() < 1e-9);
+    // clipped to the cube
+    let crossings = Quadric::new([1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.25])
+        .crossings(&ray([0.0, 5.0, 0.5], [0.0, -1.0, 0.0]));
+    assert!(crossings.is_empty());
+}
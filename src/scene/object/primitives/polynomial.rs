@@ -0,0 +1,203 @@
+/// Real roots of a x^2 + b x + c, in increasing order. The roots are found without
+/// subtracting nearly equal numbers, so both stay accurate when one is much smaller.
+pub fn quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return if b == 0.0 { vec![] } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        // b and c are both 0
+        return vec![0.0, 0.0];
+    }
+    let (root1, root2) = (q / a, c / q);
+    vec![root1.min(root2), root1.max(root2)]
+}
+
+/// Real roots of a x^3 + b x^2 + c x + d, in increasing order, with repeated roots given once
+pub fn cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    real_roots(&[a, b, c, d])
+}
+
+/// Real roots of a x^4 + b x^3 + c x^2 + d x + e, in increasing order, with repeated roots
+/// given once
+pub fn quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    real_roots(&[a, b, c, d, e])
+}
+
+/// Real roots of a x^4 + b x^3 + c x^2 + d x + e where it changes sign, in increasing order.
+/// Repeated roots where it only touches 0 are left out, so that along a ray the roots
+/// alternate between entering and leaving the region where it is negative.
+pub fn quartic_sign_changes(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    let coefficients = [a, b, c, d, e];
+    let roots = real_roots(&coefficients);
+    // the sign is constant between consecutive roots, so test it half way between them
+    let sign = |x: f64| evaluate(&coefficients, x).signum();
+    (0..roots.len())
+        .filter(|&i| {
+            let before = match i {
+                0 => roots[i] - 1.0,
+                _ => 0.5 * (roots[i - 1] + roots[i]),
+            };
+            let after = match roots.get(i + 1) {
+                Some(next) => 0.5 * (roots[i] + next),
+                None => roots[i] + 1.0,
+            };
+            sign(before) != sign(after)
+        })
+        .map(|i| roots[i])
+        .collect()
+}
+
+/// Value of the polynomial with the given coefficients, highest power first
+fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients
+        .iter()
+        .fold(0.0, |value, coefficient| value * x + coefficient)
+}
+
+/// Real roots of a polynomial of any degree, highest power first. Between consecutive roots of
+/// its derivative the polynomial is monotonic, so each of those intervals holds at most one
+/// root, which is found by Newton's method kept inside the interval by bisection. Unlike the
+/// closed forms for cubics and quartics, this stays accurate near repeated roots.
+fn real_roots(coefficients: &[f64]) -> Vec<f64> {
+    let start = coefficients
+        .iter()
+        .position(|&coefficient| coefficient != 0.0)
+        .unwrap_or(coefficients.len());
+    let coefficients = &coefficients[start..];
+    if coefficients.len() <= 3 {
+        let mut padded = [0.0; 3];
+        padded[3 - coefficients.len()..].copy_from_slice(coefficients);
+        let [a, b, c] = padded;
+        let mut roots = quadratic(a, b, c);
+        roots.dedup();
+        return roots;
+    }
+
+    let degree = coefficients.len() - 1;
+    let derivative: Vec<f64> = coefficients[..degree]
+        .iter()
+        .enumerate()
+        .map(|(i, coefficient)| coefficient * (degree - i) as f64)
+        .collect();
+    // Cauchy's bound: every root is closer to 0 than this
+    let bound = 1.0
+        + coefficients[1..]
+            .iter()
+            .map(|coefficient| (coefficient / coefficients[0]).abs())
+            .fold(0.0, f64::max);
+    let mut ends = vec![-bound];
+    ends.extend(real_roots(&derivative));
+    ends.push(bound);
+
+    let mut roots: Vec<f64> = vec![];
+    for interval in ends.windows(2) {
+        let (low, high) = (interval[0], interval[1]);
+        let (low_value, high_value) = (evaluate(coefficients, low), evaluate(coefficients, high));
+        let root = if low_value == 0.0 {
+            low
+        } else if high_value == 0.0 {
+            high
+        } else if low_value.signum() != high_value.signum() {
+            bracketed_root(coefficients, &derivative, low, high, low_value)
+        } else {
+            continue;
+        };
+        if roots.last() != Some(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+/// The root of a polynomial between `low` and `high`, where it changes sign once, starting at
+/// `low` where it has value `low_value`
+fn bracketed_root(
+    coefficients: &[f64],
+    derivative: &[f64],
+    mut low: f64,
+    mut high: f64,
+    low_value: f64,
+) -> f64 {
+    let mut x = 0.5 * (low + high);
+    for _ in 0..100 {
+        let value = evaluate(coefficients, x);
+        if value == 0.0 {
+            return x;
+        }
+        if value.signum() == low_value.signum() {
+            low = x;
+        } else {
+            high = x;
+        }
+        let step = value / evaluate(derivative, x);
+        let newton = x - step;
+        let next = if newton > low && newton < high {
+            newton
+        } else {
+            0.5 * (low + high)
+        };
+        if next == x || high - low <= f64::EPSILON * x.abs() {
+            return next;
+        }
+        x = next;
+    }
+    x
+}
+
+#[test]
+fn test_quadratic() {
+    assert_eq!(quadratic(1.0, -3.0, 2.0), vec![1.0, 2.0]);
+    assert_eq!(quadratic(2.0, 0.0, -8.0), vec![-2.0, 2.0]);
+    assert_eq!(quadratic(1.0, 0.0, 1.0), vec![]);
+    assert_eq!(quadratic(0.0, 2.0, -1.0), vec![0.5]);
+    // the naive formula loses the small root entirely
+    let roots = quadratic(1.0, 1e9, 1.0);
+    assert!((roots[1] + 1e-9).abs() < 1e-20);
+}
+
+#[test]
+fn test_quartic() {
+    let close = |roots: Vec<f64>, expected: &[f64]| {
+        roots.len() == expected.len()
+            && roots
+                .iter()
+                .zip(expected)
+                .all(|(root, expected)| (root - expected).abs() < 1e-9)
+    };
+    // (x - 1)(x - 2)(x + 3)
+    assert!(close(cubic(1.0, 0.0, -7.0, 6.0), &[-3.0, 1.0, 2.0]));
+    assert!(close(cubic(2.0, 0.0, 0.0, -16.0), &[2.0]));
+    // (x - 1)(x - 2)(x - 3)(x - 4)
+    assert!(close(
+        quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+        &[1.0, 2.0, 3.0, 4.0]
+    ));
+    // (x^2 - 1)^2, with repeated roots
+    assert!(close(quartic(1.0, 0.0, -2.0, 0.0, 1.0), &[-1.0, 1.0]));
+    assert!(close(quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]));
+    // only the roots of odd multiplicity change the sign: (x - 1)^2 (x - 2) (x - 3)
+    assert!(close(
+        quartic_sign_changes(1.0, -7.0, 17.0, -17.0, 6.0),
+        &[2.0, 3.0]
+    ));
+    assert!(close(quartic_sign_changes(1.0, 0.0, -2.0, 0.0, 1.0), &[]));
+    assert!(close(
+        quartic_sign_changes(1.0, -10.0, 35.0, -50.0, 24.0),
+        &[1.0, 2.0, 3.0, 4.0]
+    ));
+    // (x - 1)^3 (x + 1)
+    assert!(close(
+        quartic_sign_changes(1.0, -2.0, 0.0, 2.0, -1.0),
+        &[-1.0, 1.0]
+    ));
+    // roots far apart in scale: (x - 1e-3)(x - 1)(x - 10)(x - 1e3)
+    let roots = quartic(1.0, -1011.001, 11011.011, -10011.01, 10.0);
+    assert!(close(roots, &[1e-3, 1.0, 10.0, 1e3]));
+    // falls back to lower degrees
+    assert!(close(quartic(0.0, 0.0, 1.0, -3.0, 2.0), &[1.0, 2.0]));
+}
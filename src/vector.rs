@@ -64,6 +64,18 @@ impl HVector {
         self.clone() - normal.scale(2.0 * self.dot(normal))
     }
 
+    /// Bend this unit direction through a surface with unit `normal` facing against it, where
+    /// `eta` is the ratio of refractive indices n1 / n2; None on total internal reflection
+    pub fn refract(&self, normal: &HVector, eta: f64) -> Option<HVector> {
+        let cos_incident = -self.dot(normal);
+        let sin_squared_transmitted = eta * eta * (1.0 - cos_incident * cos_incident);
+        if sin_squared_transmitted > 1.0 {
+            return None;
+        }
+        let cos_transmitted = (1.0 - sin_squared_transmitted).sqrt();
+        Some(self.scale(eta) + normal.scale(eta * cos_incident - cos_transmitted))
+    }
+
     pub fn reverse(&self) -> HVector {
         self.scale(-1.0)
     }
@@ -87,6 +99,157 @@ impl Add for HVector {
     }
 }
 
+/// A position, which translations move
+#[derive(Clone)]
+pub struct Point(HVector);
+
+/// A displacement or a direction of travel, which translations leave alone
+#[derive(Clone)]
+pub struct Direction(HVector);
+
+/// The direction a surface faces. A transformation which stretches the surface would tilt a
+/// normal moved like a direction away from perpendicular, so normals are moved by the inverse
+/// transpose of the matrix instead.
+#[derive(Clone)]
+pub struct Normal(HVector);
+
+impl Point {
+    pub fn new(point: [f64; 3]) -> Point {
+        Point(HVector::new(point))
+    }
+
+    pub(crate) fn from_hvector(vector: HVector) -> Point {
+        Point(vector)
+    }
+
+    pub(crate) fn get(&self) -> &Array1<f64> {
+        self.0.get()
+    }
+
+    pub fn to_array(&self) -> [f64; 3] {
+        self.0.to_array()
+    }
+
+    /// The displacement of the point from the origin
+    pub fn from_origin(&self) -> Direction {
+        Direction(self.0.clone())
+    }
+}
+
+impl Direction {
+    pub fn new(direction: [f64; 3]) -> Direction {
+        Direction(HVector::new(direction))
+    }
+
+    pub(crate) fn from_hvector(vector: HVector) -> Direction {
+        Direction(vector)
+    }
+
+    pub(crate) fn get(&self) -> &Array1<f64> {
+        self.0.get()
+    }
+
+    pub fn to_array(&self) -> [f64; 3] {
+        self.0.to_array()
+    }
+
+    pub fn magnitude_squared(&self) -> f64 {
+        self.0.magnitude_squared()
+    }
+
+    pub fn magnitude(&self) -> f64 {
+        self.0.magnitude()
+    }
+
+    pub fn dot(&self, rhs: &Direction) -> f64 {
+        self.0.dot(&rhs.0)
+    }
+
+    pub fn cross(&self, rhs: &Direction) -> Direction {
+        Direction(self.0.cross(&rhs.0))
+    }
+
+    pub fn scale(&self, factor: f64) -> Direction {
+        Direction(self.0.scale(factor))
+    }
+
+    pub fn normalized(&self) -> Direction {
+        Direction(self.0.normalized())
+    }
+
+    pub fn reverse(&self) -> Direction {
+        Direction(self.0.reverse())
+    }
+
+    pub fn reflect(&self, normal: &Normal) -> Direction {
+        Direction(self.0.reflect(&normal.0))
+    }
+
+    /// Bend this unit direction through a surface with unit `normal` facing against it, where
+    /// `eta` is the ratio of refractive indices n1 / n2; None on total internal reflection
+    pub fn refract(&self, normal: &Normal, eta: f64) -> Option<Direction> {
+        self.0.refract(&normal.0, eta).map(Direction)
+    }
+}
+
+impl Normal {
+    pub fn new(normal: [f64; 3]) -> Normal {
+        Normal(HVector::new(normal))
+    }
+
+    pub(crate) fn from_hvector(vector: HVector) -> Normal {
+        Normal(vector)
+    }
+
+    pub(crate) fn get(&self) -> &Array1<f64> {
+        self.0.get()
+    }
+
+    pub fn to_array(&self) -> [f64; 3] {
+        self.0.to_array()
+    }
+
+    /// Cosine of the angle to a unit `direction`, if the normal is also of unit length
+    pub fn dot(&self, direction: &Direction) -> f64 {
+        self.0.dot(&direction.0)
+    }
+
+    pub fn normalized(&self) -> Normal {
+        Normal(self.0.normalized())
+    }
+
+    pub fn reverse(&self) -> Normal {
+        Normal(self.0.reverse())
+    }
+
+    /// The direction straight out of the surface
+    pub fn to_direction(&self) -> Direction {
+        Direction(self.0.clone())
+    }
+}
+
+/// The displacement from `rhs` to `self`
+impl Sub for Point {
+    type Output = Direction;
+    fn sub(self, rhs: Point) -> Direction {
+        Direction(self.0 - rhs.0)
+    }
+}
+
+impl Add<Direction> for Point {
+    type Output = Point;
+    fn add(self, rhs: Direction) -> Point {
+        Point(self.0 + rhs.0)
+    }
+}
+
+impl Add for Direction {
+    type Output = Direction;
+    fn add(self, rhs: Direction) -> Direction {
+        Direction(self.0 + rhs.0)
+    }
+}
+
 #[derive(Clone)]
 pub struct Vector3(pub Array1<f64>);
 impl Vector3 {
@@ -105,3 +268,34 @@ impl Sub for Vector3 {
         Vector3(self.0 - rhs.0)
     }
 }
+
+#[test]
+fn test_refract() {
+    let close = |a: Direction, b: [f64; 3]| {
+        let a = a.to_array();
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-12)
+    };
+    let normal = Normal::new([0.0, 1.0, 0.0]);
+    // straight through, whatever the indices
+    let down = Direction::new([0.0, -1.0, 0.0]);
+    assert!(close(
+        down.refract(&normal, 1.0 / 1.5).unwrap(),
+        [0.0, -1.0, 0.0]
+    ));
+
+    // bent towards the normal entering a denser medium, following Snell's law
+    let incident = Direction::new([1.0, -1.0, 0.0]).normalized();
+    let eta = 1.0 / 1.5;
+    let refracted = incident.refract(&normal, eta).unwrap();
+    let [x, y, _] = refracted.to_array();
+    assert!((refracted.magnitude() - 1.0).abs() < 1e-12);
+    assert!((x - eta * incident.to_array()[0]).abs() < 1e-12 && y < 0.0);
+
+    // past the critical angle leaving it, all of the light is reflected
+    let grazing = Direction::new([1.0, -0.5, 0.0]).normalized();
+    assert!(grazing.refract(&normal, 1.5).is_none());
+    assert!(close(
+        grazing.reflect(&normal),
+        Direction::new([1.0, 0.5, 0.0]).normalized().to_array()
+    ));
+}